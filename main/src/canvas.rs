@@ -0,0 +1,124 @@
+use dominator::{clone, html, Dom};
+use futures_signals::signal::SignalExt;
+use gloo_events::EventListener;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use shared::{BevyClient, BevyError, Cursor, Event, WindowId};
+
+use crate::{events, resize, Liveness};
+
+/// Creates a canvas that fills its parent
+pub fn create() -> HtmlCanvasElement {
+    let canvas = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("canvas")
+        .unwrap()
+        .dyn_into::<HtmlCanvasElement>()
+        .unwrap();
+    canvas.style().set_property("display", "block").unwrap();
+    canvas.style().set_property("width", "100%").unwrap();
+    canvas.style().set_property("height", "100%").unwrap();
+    canvas
+}
+
+/// Creates another canvas and transfers it to the worker, which renders a separate window onto
+/// it. The canvas is shown using [`view`].
+pub async fn attach(bevy_client: &BevyClient) -> Result<(WindowId, HtmlCanvasElement), BevyError> {
+    let canvas = create();
+    let offscreen_canvas = canvas.transfer_control_to_offscreen()
+        .map_err(|error| BevyError::CanvasTransferFailed(crate::describe(&error)))?;
+    let window = bevy_client.attach_canvas(offscreen_canvas).await?;
+    Ok((window, canvas))
+}
+
+/// Shows the cursor of a window over its canvas, see [`shared::Main::set_cursor`]
+pub fn set_cursor(canvas: &HtmlCanvasElement, cursor: &Cursor) {
+    let icon = match cursor.visible {
+        true => cursor.icon.as_str(),
+        false => "none",
+    };
+    canvas.style().set_property("cursor", icon).unwrap();
+}
+
+/// Handle to a canvas that was attached to a window in the worker. Dropping the handle unregisters
+/// the event listeners, disconnects the size observer, and detaches the canvas from the worker so
+/// that the window is closed and its rendering surface is released. The canvas is not detached if
+/// the worker is no longer running, since the request would never be answered.
+pub struct CanvasHandle {
+    bevy_client: BevyClient,
+    liveness: Liveness,
+    window: WindowId,
+    _listeners: Vec<EventListener>,
+    _size_observer: resize::SizeObserver,
+}
+
+impl Drop for CanvasHandle {
+    fn drop(&mut self) {
+        if !self.liveness.is_alive() {
+            return;
+        }
+        /* the request is awaited in the background since dropping it would abort it */
+        let response = self.bevy_client.detach_canvas(self.window);
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(error) = response.await {
+                web_sys::console::warn_1(&format!("could not detach canvas: {}", error).into());
+            }
+        });
+    }
+}
+
+/// Shows a canvas that fills its parent. Changes to the size of the canvas and to the device pixel
+/// ratio are forwarded to the given window, the size of its backing store is set by the worker. The
+/// canvas is detached from the worker when it is removed from the DOM.
+pub fn view(
+    bevy_client: &BevyClient,
+    liveness: &Liveness,
+    canvas: &HtmlCanvasElement,
+    window: WindowId,
+    listeners: Vec<EventListener>,
+) -> Dom {
+    let size_observer = resize::SizeObserver::new(canvas);
+    let size = size_observer.signal();
+    let handle = CanvasHandle {
+        bevy_client: bevy_client.clone(),
+        liveness: liveness.clone(),
+        window,
+        _listeners: listeners,
+        _size_observer: size_observer,
+    };
+
+    html!("div", {
+        .style("width", "100%")
+        .style("height", "100%")
+        .future(size.for_each(clone!(bevy_client =>
+            move |size| clone!(bevy_client =>
+                async move {
+                    let Some(resize::Size { width, height, physical_size }) = size else {
+                        return;
+                    };
+                    let event = Event::Resize { width, height, physical_size };
+                    if let Err(error) = bevy_client.process_event(window, event).await {
+                        web_sys::console::warn_1(&format!("could not resize: {}", error).into());
+                    }
+                }
+            )
+        )))
+        .future(events::device_pixel_ratio().for_each(clone!(bevy_client =>
+            move |scale_factor| clone!(bevy_client =>
+                async move {
+                    let event = Event::ScaleFactorChanged { scale_factor };
+                    if let Err(error) = bevy_client.process_event(window, event).await {
+                        web_sys::console::warn_1(&format!("could not change scale factor: {}", error).into());
+                    }
+                }
+            )
+        )))
+        .after_inserted(clone!(canvas => move |node| {
+            node.append_child(&canvas).unwrap();
+        }))
+        .after_removed(move |_| drop(handle))
+    })
+}
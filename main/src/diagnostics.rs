@@ -0,0 +1,32 @@
+use dominator::{html, Dom};
+use futures_signals::signal::{Mutable, SignalExt};
+
+use shared::DiagnosticsSnapshot;
+
+/// The interval at which the worker pushes diagnostics to the main thread
+pub const INTERVAL_MS: u64 = 500;
+
+/// Shows the latest diagnostics that were pushed from the worker
+pub fn overlay(snapshot: &Mutable<Option<DiagnosticsSnapshot>>) -> Dom {
+    html!("div", {
+        .style("position", "absolute")
+        .style("bottom", "0")
+        .style("left", "0")
+        .style("padding", "10px")
+        .style("font-family", "monospace")
+        .text_signal(snapshot.signal().map(|snapshot| match snapshot {
+            Some(snapshot) => {
+                let value = |value: Option<f64>, precision| match value {
+                    Some(value) => format!("{:.*}", precision, value),
+                    None => String::from("-"),
+                };
+                format!("{} fps | frame {} ms | physics {} ms | {} entities",
+                    value(snapshot.fps, 0),
+                    value(snapshot.frame_time, 1),
+                    value(snapshot.physics_step_time, 1),
+                    value(snapshot.entity_count, 0))
+            },
+            None => String::new(),
+        }))
+    })
+}
@@ -1,126 +1,195 @@
-use std::{collections::HashSet, sync::{Arc, RwLock}};
+use std::{cell::{Cell, RefCell}, collections::HashMap, rc::{Rc, Weak}};
 
 use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
 use gloo_events::{EventListener, EventListenerOptions};
 use dominator::clone;
+use futures_signals::signal::{Mutable, Signal, SignalExt};
 
-use shared::{Event, BevyClient};
+use shared::{Event, BevyClient, InputBuffer, Modifiers, TouchPhase, WindowId};
 
 pub fn register(
     canvas: &HtmlCanvasElement,
-    bevy_client: &BevyClient
+    bevy_client: &BevyClient,
+    window: WindowId,
+    input_buffer: Option<InputBuffer>,
+    pointer_lock: bool,
 ) -> Vec<EventListener> {
-    
-    /* track which buttons were pressed over the canvas */
-    let pressed_buttons: Arc<RwLock<HashSet<i16>>> = Default::default();
-    
-    /* this event clears any pointer down events when moving beyond the body */
-    let global_pointer_out = EventListener::new(
-        &dominator::body(),
-        "pointerout",
-        clone!(bevy_client, pressed_buttons => move |_| {
-            for button in pressed_buttons.write().unwrap().drain() {
-                bevy_client.process_event(
-                    Event::MouseButton { pressed: false, button }
-                );
-            }
-        }
-    ));
-
-    let global_pointer_up = EventListener::new(
-        &dominator::body(),
-        "pointerup",
-        clone!(bevy_client, pressed_buttons => move |event| {
-            let button = event.unchecked_ref::<web_sys::PointerEvent>().button();
-            if pressed_buttons.write().unwrap().remove(&button) {
-                bevy_client.process_event(
-                    Event::MouseButton { pressed: false, button }
-                );
-            }
-        }
-    ));
-    
-    let mut global_last_position = None;
-    let global_pointer_move = EventListener::new(
-        &dominator::body(),
-        "pointermove",
-        clone!(bevy_client => move |event| {
-            let event = event.unchecked_ref::<web_sys::PointerEvent>();
-            let position = (event.offset_x(), event.offset_y());
-            let delta = global_last_position
-                .map(|(last_x, last_y)| ((position.0 - last_x), (position.1 - last_y)));
-            global_last_position = Some(position);
-            if let Some(delta) = delta {
-                bevy_client.process_event(
-                    Event::MouseMotion {
-                        delta,
-                    }
-                );
-            }
-        }
-    ));
+    /* the events are written into the input buffer or sent to the worker once per animation frame */
+    let queue = EventQueue::new(bevy_client, window, input_buffer);
 
     let mut last_position = None;
     let pointer_move = EventListener::new(
         canvas,
         "pointermove",
-        clone!(bevy_client => move |event| {
+        clone!(queue, canvas => move |event| {
             let event = event.unchecked_ref::<web_sys::PointerEvent>();
+            if is_touch(event) {
+                queue.push(touch(event, TouchPhase::Moved));
+                return;
+            }
+            /* the position is frozen while the pointer is locked, so the movement is used instead */
+            if is_pointer_locked(&canvas) {
+                last_position = None;
+                queue.push(Event::MouseMotion { delta: (event.movement_x(), event.movement_y()) });
+                return;
+            }
+            /* the position is outside of the canvas if the pointer is captured and has left it */
             let position = (event.offset_x(), event.offset_y());
             let delta = last_position
                 .map(|(last_x, last_y)| ((position.0 - last_x), (position.1 - last_y)));
             last_position = Some(position);
-            bevy_client.process_event(
-                Event::CursorMoved {
-                    delta,
-                    position
-                }
-            );
+            queue.push(Event::CursorMoved { delta, position });
+            if let Some(delta) = delta {
+                queue.push(Event::MouseMotion { delta });
+            }
         }
     ));
 
+    /* the button that was pressed when the pointer was captured, until it is released */
+    let captured_button: Rc<Cell<Option<i16>>> = Default::default();
+
     let pointer_down = EventListener::new_with_options(
         canvas,
         "pointerdown",
         EventListenerOptions::enable_prevent_default(),
-        clone!(bevy_client => move |event| {
+        clone!(queue, canvas, captured_button => move |event| {
             event.prevent_default();
-            let button = event.unchecked_ref::<web_sys::PointerEvent>().button();
-            pressed_buttons.write().unwrap().insert(button);
-            bevy_client.process_event(
-                Event::MouseButton { pressed: true, button }
-            );
+            /* preventing the default action also prevents the canvas from taking focus */
+            let _ = canvas.focus();
+            let event = event.unchecked_ref::<web_sys::PointerEvent>();
+            if is_touch(event) {
+                queue.push(touch(event, TouchPhase::Started));
+                return;
+            }
+            if pointer_lock {
+                /* the browser only grants pointer lock in response to a user gesture. The pointer
+                   is not captured since locking it releases the capture and all of its events
+                   arrive here while it is locked. */
+                if !is_pointer_locked(&canvas) {
+                    canvas.request_pointer_lock();
+                }
+            } else if canvas.set_pointer_capture(event.pointer_id()).is_ok() {
+                /* the events of a captured pointer arrive here until the button is released, even
+                   if it leaves the canvas, e.g., when a drag overshoots the edge of the canvas */
+                captured_button.set(Some(event.button()));
+            }
+            queue.push(Event::MouseButton { pressed: true, button: event.button() });
+        }
+    ));
+
+    /* pointers are captured by the canvas while pressed, so these events always arrive here */
+    let pointer_up = EventListener::new(
+        canvas,
+        "pointerup",
+        clone!(queue, captured_button => move |event| {
+            let event = event.unchecked_ref::<web_sys::PointerEvent>();
+            if is_touch(event) {
+                queue.push(touch(event, TouchPhase::Ended));
+                return;
+            }
+            if captured_button.get() == Some(event.button()) {
+                captured_button.set(None);
+            }
+            queue.push(Event::MouseButton { pressed: false, button: event.button() });
+        }
+    ));
+
+    /* the capture can be lost without a pointerup event, e.g., when the window loses focus while
+       the button is held, in which case the button is released here so that it is not stuck */
+    let lost_pointer_capture = EventListener::new(
+        canvas,
+        "lostpointercapture",
+        clone!(queue => move |event| {
+            if is_touch(event.unchecked_ref::<web_sys::PointerEvent>()) {
+                return;
+            }
+            if let Some(button) = captured_button.take() {
+                queue.push(Event::MouseButton { pressed: false, button });
+            }
+        }
+    ));
+
+    let pointer_cancel = EventListener::new(
+        canvas,
+        "pointercancel",
+        clone!(queue => move |event| {
+            let event = event.unchecked_ref::<web_sys::PointerEvent>();
+            if is_touch(event) {
+                queue.push(touch(event, TouchPhase::Canceled));
+            }
         }
     ));
 
     let pointer_over = EventListener::new(
         canvas,
         "pointerover",
-        clone!(bevy_client => move |_| {
-            bevy_client.process_event(Event::CursorEntered);
+        clone!(queue => move |event| {
+            if !is_touch(event.unchecked_ref::<web_sys::PointerEvent>()) {
+                queue.push(Event::CursorEntered);
+            }
         }
     ));
 
     let pointer_out = EventListener::new(
         canvas,
         "pointerout",
-        clone!(bevy_client => move |_| {
-            bevy_client.process_event(Event::CursorLeft);
+        clone!(queue => move |event| {
+            if !is_touch(event.unchecked_ref::<web_sys::PointerEvent>()) {
+                queue.push(Event::CursorLeft);
+            }
         }
     ));
 
     let wheel = EventListener::new(
         canvas,
         "wheel",
-        clone!(bevy_client => move |event| {
+        clone!(queue => move |event| {
             let event = event.unchecked_ref::<web_sys::WheelEvent>();
-            bevy_client.process_event(
-                Event::MouseWheel {
-                    delta: (event.delta_x(), event.delta_y()),
-                    unit: event.delta_mode()
-                }
-            );
+            queue.push(Event::MouseWheel {
+                delta: (event.delta_x(), event.delta_y()),
+                unit: event.delta_mode()
+            });
+        }
+    ));
+
+    /* the logical keys of the pressed physical keys, which are released when focus is lost */
+    let pressed_keys: Rc<RefCell<HashMap<String, String>>> = Default::default();
+
+    let key_down = EventListener::new(
+        canvas,
+        "keydown",
+        clone!(queue, pressed_keys => move |event| {
+            let event = event.unchecked_ref::<web_sys::KeyboardEvent>();
+            pressed_keys.borrow_mut().insert(event.code(), event.key());
+            queue.push(keyboard_input(event, true));
+        }
+    ));
+
+    let key_up = EventListener::new(
+        canvas,
+        "keyup",
+        clone!(queue, pressed_keys => move |event| {
+            let event = event.unchecked_ref::<web_sys::KeyboardEvent>();
+            pressed_keys.borrow_mut().remove(&event.code());
+            queue.push(keyboard_input(event, false));
+        }
+    ));
+
+    /* keys that are released after the canvas lost focus send their keyup events elsewhere */
+    let blur = EventListener::new(
+        canvas,
+        "blur",
+        clone!(queue => move |_| {
+            for (code, key) in pressed_keys.borrow_mut().drain() {
+                queue.push(Event::KeyboardInput {
+                    pressed: false,
+                    code,
+                    key,
+                    repeat: false,
+                    modifiers: Modifiers::default(),
+                });
+            }
         }
     ));
 
@@ -133,15 +202,190 @@ pub fn register(
         event.prevent_default();
     });
 
-    vec![
-        global_pointer_up,
-        global_pointer_out,
-        global_pointer_move,
+    let mut listeners = vec![
         pointer_move,
         pointer_down,
+        pointer_up,
+        lost_pointer_capture,
+        pointer_cancel,
         pointer_over,
         pointer_out,
         wheel,
+        key_down,
+        key_up,
+        blur,
         context_menu
-    ]
+    ];
+
+    /* report when the pointer is locked to the canvas or released, e.g., by pressing escape */
+    if pointer_lock {
+        let mut locked = false;
+        listeners.push(EventListener::new(
+            &web_sys::window().unwrap().document().unwrap(),
+            "pointerlockchange",
+            clone!(queue, canvas => move |_| {
+                let is_locked = is_pointer_locked(&canvas);
+                if is_locked != locked {
+                    locked = is_locked;
+                    queue.push(Event::PointerLockChanged { locked });
+                }
+            }
+        )));
+    }
+
+    listeners
+}
+
+/// Returns a signal of `window.devicePixelRatio`, which changes when the page is zoomed or when the
+/// window is moved to a display with a different pixel density
+pub fn device_pixel_ratio() -> impl Signal<Item = f64> {
+    let ratio = Mutable::new(web_sys::window().unwrap().device_pixel_ratio());
+    let listener = Rc::new(RefCell::new(None));
+    watch_device_pixel_ratio(ratio.clone(), Rc::downgrade(&listener));
+    /* the listener is dropped together with the signal */
+    ratio.signal().map(move |ratio| {
+        let _ = &listener;
+        ratio
+    })
+}
+
+fn watch_device_pixel_ratio(ratio: Mutable<f64>, listener: Weak<RefCell<Option<EventListener>>>) {
+    let Some(listener_ref) = listener.upgrade() else {
+        return;
+    };
+    /* the media query only matches the current ratio, so it is replaced each time it changes */
+    let query = format!("(resolution: {}dppx)", ratio.get());
+    let Ok(Some(media_query_list)) = web_sys::window().unwrap().match_media(&query) else {
+        return;
+    };
+    *listener_ref.borrow_mut() = Some(EventListener::once(&media_query_list, "change", move |_| {
+        ratio.set_neq(web_sys::window().unwrap().device_pixel_ratio());
+        watch_device_pixel_ratio(ratio, listener);
+    }));
+}
+
+/// Collects the input events of a window and sends them to the worker once per animation frame.
+/// Motion events are coalesced, i.e., the deltas of `MouseMotion` events are summed and only the
+/// latest position of `CursorMoved` events is kept. If an input buffer is given, the events that
+/// it can represent are written into it immediately instead, unless events sent using RPC have not
+/// been processed yet, since the worker drains the buffer before it processes them.
+#[derive(Clone)]
+pub struct EventQueue {
+    bevy_client: BevyClient,
+    window: WindowId,
+    input_buffer: Option<InputBuffer>,
+    events: Rc<RefCell<Vec<Event>>>,
+    /// the number of requests to process events that have not been answered yet
+    in_flight: Rc<Cell<usize>>,
+}
+
+impl EventQueue {
+    pub fn new(bevy_client: &BevyClient, window: WindowId, input_buffer: Option<InputBuffer>) -> EventQueue {
+        EventQueue {
+            bevy_client: bevy_client.clone(),
+            window,
+            input_buffer,
+            events: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+
+    pub fn push(&self, event: Event) {
+        let mut events = self.events.borrow_mut();
+        /* fall back to RPC for events that the buffer can not represent and when it is full. Once
+           an event has fallen back, the following events are also sent using RPC until it has been
+           processed, otherwise they could be drained from the buffer before it. */
+        if let Some(input_buffer) = &self.input_buffer {
+            if events.is_empty() && self.in_flight.get() == 0 && input_buffer.push(self.window, &event) {
+                return;
+            }
+        }
+        /* the first event of a frame requests the animation frame on which the events are sent */
+        if events.is_empty() {
+            let queue = self.clone();
+            web_sys::window()
+                .unwrap()
+                .request_animation_frame(Closure::once_into_js(move || queue.flush()).unchecked_ref())
+                .unwrap();
+        }
+        if let Some(event) = coalesce(&mut events, event) {
+            events.push(event);
+        }
+    }
+
+    /// Sends the queued events without waiting for them to be processed. The request is awaited
+    /// in the background since dropping it would abort the request, errors are reported to the
+    /// console.
+    fn flush(&self) {
+        let events = std::mem::take(&mut *self.events.borrow_mut());
+        let response = self.bevy_client.process_events(self.window, events);
+        let in_flight = self.in_flight.clone();
+        in_flight.set(in_flight.get() + 1);
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = response.await;
+            in_flight.set(in_flight.get() - 1);
+            if let Err(error) = result {
+                web_sys::console::warn_1(&format!("could not process events: {}", error).into());
+            }
+        });
+    }
+}
+
+/// Merges a motion event into a queued motion event of the same kind, unless another kind of event
+/// (e.g., a button press) was queued after it. Returns the event if it was not merged.
+fn coalesce(events: &mut [Event], event: Event) -> Option<Event> {
+    let queued = events.iter_mut()
+        .rev()
+        .take_while(|queued| matches!(queued, Event::MouseMotion { .. } | Event::CursorMoved { .. }))
+        .find(|queued| std::mem::discriminant(&**queued) == std::mem::discriminant(&event));
+    match (queued, event) {
+        (Some(Event::MouseMotion { delta }), Event::MouseMotion { delta: (x, y) }) => {
+            *delta = (delta.0 + x, delta.1 + y);
+            None
+        },
+        (Some(Event::CursorMoved { delta, position }), Event::CursorMoved { delta: next_delta, position: next_position }) => {
+            /* the delta remains unknown if the delta of either event is unknown */
+            *delta = delta.zip(next_delta).map(|((x, y), (next_x, next_y))| (x + next_x, y + next_y));
+            *position = next_position;
+            None
+        },
+        (_, event) => Some(event),
+    }
+}
+
+fn is_pointer_locked(canvas: &HtmlCanvasElement) -> bool {
+    web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .pointer_lock_element()
+        .is_some_and(|element| element.is_same_node(Some(canvas)))
+}
+
+fn is_touch(event: &web_sys::PointerEvent) -> bool {
+    event.pointer_type() == "touch"
+}
+
+fn touch(event: &web_sys::PointerEvent, phase: TouchPhase) -> Event {
+    Event::Touch {
+        phase,
+        id: event.pointer_id(),
+        position: (event.offset_x(), event.offset_y()),
+        force: Some(event.pressure() as f64),
+    }
+}
+
+fn keyboard_input(event: &web_sys::KeyboardEvent, pressed: bool) -> Event {
+    Event::KeyboardInput {
+        pressed,
+        code: event.code(),
+        key: event.key(),
+        repeat: event.repeat(),
+        modifiers: Modifiers {
+            shift: event.shift_key(),
+            control: event.ctrl_key(),
+            alt: event.alt_key(),
+            meta: event.meta_key(),
+        },
+    }
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use futures_signals::signal::{Mutable, Signal};
+use gloo_events::EventListener;
+use wasm_bindgen::prelude::*;
+
+/// The health of the worker that runs Bevy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerHealth {
+    /// the worker is starting or running
+    Running,
+    /// the worker panicked or reported an error and no longer processes requests
+    Crashed,
+    /// a new worker is being started to replace the crashed worker
+    Restarting,
+}
+
+/// Describes why the worker crashed
+#[derive(Debug, Clone)]
+pub struct Crash {
+    pub message: String,
+    pub location: Option<String>,
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} at {}", self.message, location),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Tracks the health of the worker and the reason for the last crash
+#[derive(Clone)]
+pub struct Health {
+    health: Mutable<WorkerHealth>,
+    crash: Mutable<Option<Crash>>,
+}
+
+impl Health {
+    pub fn new() -> Health {
+        Health {
+            health: Mutable::new(WorkerHealth::Running),
+            crash: Mutable::new(None),
+        }
+    }
+
+    pub fn signal(&self) -> impl Signal<Item = WorkerHealth> {
+        self.health.signal()
+    }
+
+    pub fn crash_signal(&self) -> impl Signal<Item = Option<Crash>> {
+        self.crash.signal_cloned()
+    }
+
+    /// Marks the worker as restarting, the crash is retained until the new worker is running
+    pub fn restarting(&self) {
+        self.health.set_neq(WorkerHealth::Restarting);
+    }
+
+    pub fn running(&self) {
+        self.crash.set(None);
+        self.health.set_neq(WorkerHealth::Running);
+    }
+
+    /// Marks the worker as crashed. Only the first report is kept since a panic is followed by an
+    /// error event when the worker aborts.
+    pub fn report_crash(&self, crash: Crash) {
+        if self.health.get() == WorkerHealth::Crashed {
+            return;
+        }
+        web_sys::console::error_1(&format!("bevy worker crashed: {}", crash).into());
+        self.crash.set(Some(crash));
+        self.health.set(WorkerHealth::Crashed);
+    }
+
+    /// Reports a crash when the worker throws an uncaught error or when a message from the
+    /// worker could not be deserialized
+    pub fn watch(&self, worker: &web_sys::Worker) -> Vec<EventListener> {
+        let health = self.clone();
+        let error = EventListener::new(worker, "error", move |event| {
+            let crash = match event.dyn_ref::<web_sys::ErrorEvent>() {
+                Some(event) => Crash {
+                    message: event.message(),
+                    location: Some(format!("{}:{}:{}", event.filename(), event.lineno(), event.colno())),
+                },
+                None => Crash {
+                    message: String::from("the worker reported an error"),
+                    location: None,
+                },
+            };
+            health.report_crash(crash);
+        });
+        let health = self.clone();
+        let message_error = EventListener::new(worker, "messageerror", move |_| {
+            health.report_crash(Crash {
+                message: String::from("a message from the worker could not be deserialized"),
+                location: None,
+            });
+        });
+        vec![error, message_error]
+    }
+}
@@ -0,0 +1,218 @@
+use std::{cell::{Cell, OnceCell}, pin::pin, rc::Rc};
+
+use dominator::{clone, Dom};
+use futures::{channel::oneshot, future::{self, AbortHandle}, FutureExt};
+use gloo_events::EventListener;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use shared::{BevyClient, BevyError, InputBuffer, WindowId};
+
+pub mod canvas;
+pub mod events;
+mod resize;
+
+/// The number of input events that the shared input buffer can hold
+const INPUT_BUFFER_CAPACITY: u32 = 1024;
+
+#[allow(non_snake_case)]
+#[wasm_bindgen(inline_js = "
+export function newWorker() {
+    return new Worker(new URL('../../worker.js', import.meta.url), {
+        type: 'module'
+    });
+}
+"
+)]
+extern "C" {
+    /// Starts the worker that is built from `worker.js`, which is expected next to the JavaScript
+    /// bindings of the main crate
+    #[wasm_bindgen(js_name = newWorker, catch)]
+    pub fn spawn_worker() -> Result<web_sys::Worker, JsValue>;
+}
+
+/// Sends an app-specific command to the worker, which emits it as an event if it has been
+/// registered
+pub async fn send_command<T: shared::RpcCommand>(
+    bevy_client: &BevyClient,
+    command: &T,
+) -> Result<(), BevyError> {
+    let payload = serde_json::to_string(command)
+        .map_err(|error| BevyError::InvalidCommand(error.to_string()))?;
+    bevy_client.send_command(String::from(T::TAG), payload).await
+}
+
+/// Returns the message of a JavaScript error
+pub(crate) fn describe(error: &JsValue) -> String {
+    match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => format!("{:?}", error),
+    }
+}
+
+/// Whether the worker of a [`BevyHandle`] is still running, i.e., it has neither crashed nor been
+/// terminated. Requests to a worker that is not running are never answered.
+#[derive(Clone)]
+pub struct Liveness(Rc<Cell<bool>>);
+
+impl Liveness {
+    pub fn is_alive(&self) -> bool {
+        self.0.get()
+    }
+}
+
+/// Handle to a Bevy app that runs in a web worker. Dropping the handle stops serving requests
+/// from the worker and terminates it.
+pub struct BevyHandle {
+    worker: web_sys::Worker,
+    client: BevyClient,
+    server: AbortHandle,
+    liveness: Liveness,
+    /// marks the worker as crashed when it throws an uncaught error, e.g., after a panic
+    _crashed: EventListener,
+    /// the canvas of the primary window
+    canvas: HtmlCanvasElement,
+    /// the buffer that input events are written into, if the page is cross-origin isolated
+    input_buffer: OnceCell<InputBuffer>,
+    /// whether the canvas of the primary window locks the pointer when it is pressed
+    pointer_lock: bool,
+}
+
+impl BevyHandle {
+    /// Spawns the worker using [`spawn_worker`] and connects to it, see [`BevyHandle::new`]
+    pub async fn spawn(main: impl shared::Main + 'static) -> Result<BevyHandle, BevyError> {
+        let worker = spawn_worker()
+            .map_err(|error| BevyError::WorkerFailed(describe(&error)))?;
+        BevyHandle::new(worker, main).await
+    }
+
+    /// Connects to a worker, calls from the worker to the main thread are handled by `main`.
+    /// Returns an error (and terminates the worker) if the worker throws an error before it is
+    /// connected, e.g., because its script could not be loaded.
+    pub async fn new(worker: web_sys::Worker, main: impl shared::Main + 'static) -> Result<BevyHandle, BevyError> {
+        /* the handshake is never answered by a worker that failed to start */
+        let (failed_tx, failed_rx) = oneshot::channel();
+        let mut failed_tx = Some(failed_tx);
+        let failed = EventListener::new(&worker, "error", move |event| {
+            let message = match event.dyn_ref::<web_sys::ErrorEvent>() {
+                Some(event) => event.message(),
+                None => String::from("the worker reported an error"),
+            };
+            if let Some(failed_tx) = failed_tx.take() {
+                let _ = failed_tx.send(message);
+            }
+        });
+        let interface = match future::select(pin!(web_rpc::Interface::new(worker.clone())), failed_rx).await {
+            future::Either::Left((interface, _)) => interface,
+            future::Either::Right((message, _)) => {
+                worker.terminate();
+                return Err(BevyError::WorkerFailed(message.unwrap_or_default()));
+            },
+        };
+        drop(failed);
+        let (client, server) = web_rpc::Builder::new(interface)
+            .with_service::<shared::MainService<_>>(main)
+            .with_client::<BevyClient>()
+            .build();
+        let (server, server_handle) = future::abortable(server);
+        wasm_bindgen_futures::spawn_local(server.map(drop));
+
+        /* create a canvas for Bevy to render onto */
+        let canvas = canvas::create();
+        /* make the canvas focusable so that it can receive keyboard events */
+        canvas.set_tab_index(0);
+        canvas.style().set_property("outline", "none").unwrap();
+        /* stop the browser from panning and zooming the page when the canvas is touched */
+        canvas.style().set_property("touch-action", "none").unwrap();
+
+        let liveness = Liveness(Rc::new(Cell::new(true)));
+        let crashed = EventListener::new(&worker, "error", clone!(liveness => move |_| {
+            liveness.0.set(false);
+        }));
+
+        Ok(BevyHandle {
+            worker,
+            client,
+            server: server_handle,
+            liveness,
+            _crashed: crashed,
+            canvas,
+            input_buffer: OnceCell::new(),
+            pointer_lock: false,
+        })
+    }
+
+    /// Locks the pointer to the canvas of the primary window when it is pressed, so that
+    /// `MouseMotion` events are not limited by the edges of the screen, e.g., for mouse look. The
+    /// state of the lock is reflected by `Window::cursor.grab_mode` in the worker.
+    pub fn with_pointer_lock(mut self, pointer_lock: bool) -> BevyHandle {
+        self.pointer_lock = pointer_lock;
+        self
+    }
+
+    pub fn client(&self) -> &BevyClient {
+        &self.client
+    }
+
+    /// The worker that runs the app, e.g., for listening to its errors
+    pub fn worker(&self) -> &web_sys::Worker {
+        &self.worker
+    }
+
+    /// Whether the worker is still running, e.g., for showing additional canvases using
+    /// [`canvas::view`]
+    pub fn liveness(&self) -> &Liveness {
+        &self.liveness
+    }
+
+    /// Stops serving requests from the worker and terminates it, e.g., after the main thread has
+    /// been notified that it panicked. Canvases that are removed afterwards are not detached.
+    pub fn terminate(&self) {
+        self.liveness.0.set(false);
+        self.server.abort();
+        self.worker.terminate();
+    }
+
+    /// The canvas of the primary window, e.g., for applying the cursor using [`canvas::set_cursor`]
+    pub fn canvas(&self) -> &HtmlCanvasElement {
+        &self.canvas
+    }
+
+    /// Transfers the canvas of the primary window to the worker and initialises the app. If the
+    /// page is cross-origin isolated, an [`InputBuffer`] is shared with the worker so that pointer
+    /// and wheel events bypass RPC, otherwise all input events are sent using RPC.
+    pub async fn init(&self, arena: shared::ArenaDesc) -> Result<(), BevyError> {
+        /* a canvas can only be transferred once */
+        let offscreen_canvas = self.canvas.transfer_control_to_offscreen()
+            .map_err(|_| BevyError::AlreadyInitialised)?;
+        self.client.init(offscreen_canvas, arena).await?;
+        if let Some(input_buffer) = InputBuffer::new(INPUT_BUFFER_CAPACITY) {
+            match self.client.attach_input_buffer(input_buffer.buffer().clone()).await {
+                Ok(()) => drop(self.input_buffer.set(input_buffer)),
+                Err(error) => web_sys::console::warn_1(
+                    &format!("could not attach input buffer, using RPC instead: {}", error).into()
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates another canvas and transfers it to the worker, see [`canvas::attach`]
+    pub async fn attach_canvas(&self) -> Result<(WindowId, HtmlCanvasElement), BevyError> {
+        canvas::attach(&self.client).await
+    }
+
+    /// Shows the canvas of the primary window and forwards its input events to the worker. The
+    /// shared input buffer is only used if the app was initialised before the canvas is shown.
+    pub fn view(&self) -> Dom {
+        let input_buffer = self.input_buffer.get().cloned();
+        let listeners = events::register(&self.canvas, &self.client, WindowId::PRIMARY, input_buffer, self.pointer_lock);
+        canvas::view(&self.client, &self.liveness, &self.canvas, WindowId::PRIMARY, listeners)
+    }
+}
+
+impl Drop for BevyHandle {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
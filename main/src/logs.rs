@@ -0,0 +1,83 @@
+use dominator::{clone, events, html, with_node, Dom};
+use futures_signals::{signal::{Mutable, SignalExt}, signal_vec::{MutableVec, SignalVecExt}};
+use web_sys::HtmlInputElement;
+
+use shared::{BevyClient, LogLevel, LogRecord};
+
+/// The number of records that are retained for the log panel
+const MAX_RECORDS: usize = 200;
+
+/// The most recent log records that were forwarded from the worker
+#[derive(Clone, Default)]
+pub struct Logs {
+    records: MutableVec<LogRecord>,
+}
+
+impl Logs {
+    pub fn push(&self, record: LogRecord) {
+        let mut records = self.records.lock_mut();
+        if records.len() == MAX_RECORDS {
+            records.remove(0);
+        }
+        records.push_cloned(record);
+    }
+}
+
+/// A collapsible panel that shows the log records and allows the log filter to be changed
+pub fn panel(logs: &Logs, bevy_client: &BevyClient) -> Dom {
+    let filter = Mutable::new(String::from(shared::DEFAULT_LOG_FILTER));
+    let filter_error = Mutable::new(None);
+
+    html!("details", {
+        .style("position", "absolute")
+        .style("bottom", "0")
+        .style("right", "0")
+        .style("max-width", "50%")
+        .style("padding", "10px")
+        .style("background", "rgba(255, 255, 255, 0.8)")
+        .children(&mut [
+            html!("summary", {
+                .text("Logs")
+            }),
+            html!("div", {
+                .children(&mut [
+                    html!("input" => HtmlInputElement, {
+                        .prop("value", shared::DEFAULT_LOG_FILTER)
+                        .with_node!(input => {
+                            .event(clone!(filter => move |_: events::Input| {
+                                filter.set(input.value());
+                            }))
+                        })
+                    }),
+                    html!("button", {
+                        .text("Apply")
+                        .event(clone!(bevy_client, filter, filter_error => move |_: events::Click| {
+                            wasm_bindgen_futures::spawn_local(clone!(bevy_client, filter, filter_error => async move {
+                                let result = bevy_client.set_log_filter(filter.get_cloned()).await;
+                                filter_error.set(result.err().map(|error| error.to_string()));
+                            }));
+                        }))
+                    }),
+                    html!("span", {
+                        .style("color", "red")
+                        .text_signal(filter_error.signal_cloned().map(Option::unwrap_or_default))
+                    }),
+                ])
+            }),
+            html!("pre", {
+                .style("max-height", "200px")
+                .style("overflow", "auto")
+                .style("font-size", "small")
+                .children_signal_vec(logs.records.signal_vec_cloned().map(|record| html!("div", {
+                    .style("color", match record.level {
+                        LogLevel::Error => "red",
+                        LogLevel::Warn => "darkorange",
+                        LogLevel::Info => "black",
+                        LogLevel::Debug | LogLevel::Trace => "gray",
+                    })
+                    .text(&format!("{:?} {}: {}", record.level, record.target, record.message))
+                })))
+            }),
+        ])
+    })
+}
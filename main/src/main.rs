@@ -1,73 +1,481 @@
 
-use dominator::{clone, html};
-use futures_signals::signal::SignalExt;
+use std::{pin::pin, time::Duration};
+
+use discard::Discard;
+use dominator::{clone, html, Dom};
+use futures::future;
+use futures_signals::{map_ref, signal::{Mutable, SignalExt}, signal_vec::{MutableVec, SignalVecExt}};
+use glam::{Quat, Vec3};
 use wasm_bindgen::prelude::*;
 use web_sys::HtmlCanvasElement;
 
-mod events;
+mod diagnostics;
+mod health;
+mod logs;
+
+/// The id of the element that hosts the user interface unless the `container` query parameter is
+/// given
+const DEFAULT_CONTAINER_ID: &str = "bevy";
 
-#[allow(non_snake_case)]
-#[wasm_bindgen(inline_js = "
-export function newWorker() {
-    return new Worker(new URL('../../worker.js', import.meta.url), {
-        type: 'module'
-    });
+/// Service that allows the worker to call into the main thread
+struct MainServerImpl {
+    /// the last notification received from the worker
+    notification: Mutable<Option<shared::Notification>>,
+    health: health::Health,
+    logs: logs::Logs,
+    /// the last diagnostics pushed from the worker
+    diagnostics: Mutable<Option<shared::DiagnosticsSnapshot>>,
+    /// the cursor of the primary window
+    cursor: Mutable<shared::Cursor>,
 }
-"
-)]
-extern "C" {
-    #[wasm_bindgen(js_name = newWorker, catch)]
-    pub fn bevy() -> Result<web_sys::Worker, JsValue>;
+
+impl shared::Main for MainServerImpl {
+    fn notify(&self, notification: shared::Notification) {
+        self.notification.set(Some(notification));
+    }
+
+    fn panic(&self, message: String, location: Option<String>) {
+        self.health.report_crash(health::Crash { message, location });
+    }
+
+    fn log(&self, record: shared::LogRecord) {
+        self.logs.push(record);
+    }
+
+    fn diagnostics(&self, snapshot: shared::DiagnosticsSnapshot) {
+        self.diagnostics.set(Some(snapshot));
+    }
+
+    fn set_cursor(&self, cursor: shared::Cursor) {
+        self.cursor.set_neq(cursor);
+    }
 }
 
 #[wasm_bindgen(main)]
 pub async fn main() {
+    let health = health::Health::new();
+    /* the log records are retained across restarts so that the cause of a crash can be found */
+    let logs = logs::Logs::default();
+    /* replace crashed workers automatically if the `respawn` query parameter is present */
+    let respawn = query_param("respawn").is_some();
+    let container = container();
+    dominator::append_dom(&container, health_status(&health));
+    loop {
+        if let Err(error) = run(&container, &health, &logs).await {
+            show_failure(&container, &error);
+            return;
+        }
+        /* the worker crashed, wait for the user to restart it */
+        match respawn {
+            true => health.restarting(),
+            false => {
+                health.signal().wait_for(health::WorkerHealth::Restarting).await;
+            }
+        }
+    }
+}
+
+/// Starts a Bevy web worker and shows the simulation until the worker crashes
+async fn run(
+    container: &web_sys::HtmlElement,
+    health: &health::Health,
+    logs: &logs::Logs,
+) -> Result<(), shared::BevyError> {
     /* start the Bevy web worker */
-    let bevy_worker = bevy().expect("could not create bevy worker");
-    let bevy_interface = web_rpc::Interface::new(bevy_worker).await;
-    let bevy_client = web_rpc::Builder::new(bevy_interface)
-        .with_client::<shared::BevyClient>()
-        .build();
-
-    /* create a canvas for Bevy to render onto */
-    let canvas = web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .create_element("canvas")
-        .unwrap()
-        .dyn_into::<HtmlCanvasElement>()
-        .unwrap();
-
-    /* create the offscreen canvas and start Bevy */
-    let offscreen_canvas = canvas.transfer_control_to_offscreen()
-        .expect("could not transfer control to offscreen");
-    bevy_client.init(offscreen_canvas).await
-        .expect("could not initialize bevy");
-    bevy_client.start(std::time::Duration::from_millis(25));
-
-    let canvas_size = dominator::window_size()
-        .map(|size| ((size.width - 200.0).max(0.0) as u32, (size.height - 200.0).max(0.0) as u32))
-        .broadcast();
-
-    dominator::append_dom(&dominator::body(), html!("div", {
-        .style("padding", "100px")
+    let notification = Mutable::new(None);
+    let diagnostics = Mutable::new(None);
+    let cursor = Mutable::new(shared::Cursor::default());
+    let bevy = bevy_main::BevyHandle::spawn(MainServerImpl {
+        notification: notification.clone(),
+        health: health.clone(),
+        logs: logs.clone(),
+        diagnostics: diagnostics.clone(),
+        cursor: cursor.clone(),
+    }).await?
+        /* lock the pointer to the canvas if the `pointerlock` query parameter is present */
+        .with_pointer_lock(query_param("pointerlock").is_some());
+    let watchers = health.watch(bevy.worker());
+    let bevy_client = bevy.client();
+
+    /* transfer the canvas and start Bevy */
+    let started = async {
+        bevy.init(arena().await).await?;
+        /* show a top-down view of the arena if the `minimap` query parameter is present */
+        let minimap = match query_param("minimap") {
+            Some(_) => show_minimap(bevy_client).await?,
+            None => Minimap::Hidden,
+        };
+        bevy_client.start(shared::Pacing::AnimationFrame).await?;
+        bevy_client.subscribe_diagnostics(Some(Duration::from_millis(diagnostics::INTERVAL_MS))).await?;
+        Ok(minimap)
+    };
+    /* requests to a crashed worker are never answered, so stop waiting once it has crashed */
+    let crashed = health.signal().wait_for(health::WorkerHealth::Crashed);
+    let result = match future::select(pin!(started), crashed).await {
+        future::Either::Left((Ok(minimap), crashed)) => {
+            health.running();
+            let app = dominator::append_dom(container, app(&bevy, minimap, &notification, &diagnostics, &cursor, logs));
+            crashed.await;
+            /* terminate the worker first so that the canvases are not detached from it */
+            bevy.terminate();
+            app.discard();
+            Ok(())
+        },
+        future::Either::Left((Err(error), _)) => Err(error),
+        future::Either::Right(_) => Ok(()),
+    };
+    /* dropping the handle terminates the worker */
+    drop(bevy);
+    drop(watchers);
+    result
+}
+
+/// The user interface for a running worker
+fn app(
+    bevy: &bevy_main::BevyHandle,
+    minimap: Minimap,
+    notification: &Mutable<Option<shared::Notification>>,
+    diagnostics: &Mutable<Option<shared::DiagnosticsSnapshot>>,
+    cursor: &Mutable<shared::Cursor>,
+    logs: &logs::Logs,
+) -> Dom {
+    let bevy_client = bevy.client();
+    let minimap = Mutable::new(minimap);
+    let canvas = bevy.canvas().clone();
+    let liveness = bevy.liveness().clone();
+
+    /* objects that were added from the user interface */
+    let objects = MutableVec::new();
+    let paused = Mutable::new(false);
+    let slow_motion = Mutable::new(false);
+    let frozen = Mutable::new(false);
+
+    html!("div", {
+        .style("position", "relative")
         .style("width", "100%")
         .style("height", "100%")
-        .future(canvas_size.signal().for_each(clone!(bevy_client =>
-            move |(width, height)| clone!(bevy_client =>
-                async move {
-                    bevy_client.process_event(shared::Event::Resize { width, height });
-                }
-            )
-        )))
-        .after_inserted(clone!(canvas => move |node| {
-            node.append_child(&canvas).unwrap();
+        .child(bevy.view())
+        /* show the cursor that was set in the worker, e.g., when hovering a draggable object */
+        .future(cursor.signal_cloned().for_each(clone!(canvas => move |cursor| {
+            bevy_main::canvas::set_cursor(&canvas, &cursor);
+            async {}
+        })))
+        /* the canvas of the minimap is detached from the worker when it is removed */
+        .child_signal(minimap.signal_cloned().map(clone!(bevy_client, liveness => move |minimap| match minimap {
+            Minimap::Canvas(window, canvas) => Some(html!("div", {
+                .style("position", "absolute")
+                .style("top", "50px")
+                .style("right", "10px")
+                .style("width", "25%")
+                .style("aspect-ratio", "1")
+                .style("border", "1px solid gray")
+                .child(bevy_main::canvas::view(&bevy_client, &liveness, &canvas, window, Vec::new()))
+            })),
+            _ => None,
+        })))
+        .child(html!("div", {
+            .style("position", "absolute")
+            .style("top", "0")
+            .style("left", "0")
+            .style("padding", "10px")
+            .text_signal(notification.signal_cloned().map(|notification| match notification {
+                Some(notification) => format!("{:?}", notification),
+                None => String::from("Loading..."),
+            }))
+        }))
+        .child(html!("div", {
+            .style("position", "absolute")
+            .style("top", "0")
+            .style("right", "0")
+            .style("padding", "10px")
+            .children(&mut [
+                html!("button", {
+                    .text("Add ball")
+                    .event(clone!(bevy_client, objects => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client, objects => async move {
+                            if let Ok(object) = bevy_client.spawn_object(random_ball()).await {
+                                objects.lock_mut().push(object);
+                            }
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text("Remove ball")
+                    .prop_signal("disabled", objects.signal_vec().len().map(|len| len == 0))
+                    .event(clone!(bevy_client, objects => move |_: dominator::events::Click| {
+                        if let Some(object) = objects.lock_mut().pop() {
+                            wasm_bindgen_futures::spawn_local(clone!(bevy_client => async move {
+                                let _ = bevy_client.despawn(object).await;
+                            }));
+                        }
+                    }))
+                }),
+                html!("button", {
+                    .text_signal(paused.signal().map(|paused| match paused {
+                        true => "Resume",
+                        false => "Pause",
+                    }))
+                    .event(clone!(bevy_client, paused => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client, paused => async move {
+                            let result = match paused.get() {
+                                true => bevy_client.resume().await,
+                                false => bevy_client.pause().await,
+                            };
+                            if result.is_ok() {
+                                paused.set_neq(!paused.get());
+                            }
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text("Step")
+                    .prop_signal("disabled", paused.signal().map(|paused| !paused))
+                    .event(clone!(bevy_client => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client => async move {
+                            let _ = bevy_client.step(1).await;
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text_signal(slow_motion.signal().map(|slow_motion| match slow_motion {
+                        true => "Normal speed",
+                        false => "Slow motion",
+                    }))
+                    .event(clone!(bevy_client, slow_motion => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client, slow_motion => async move {
+                            let enable = !slow_motion.get();
+                            let result = configure_physics(&bevy_client, |config| {
+                                config.time_scale = if enable { 0.25 } else { 1.0 };
+                            }).await;
+                            if result.is_ok() {
+                                slow_motion.set_neq(enable);
+                            }
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text_signal(frozen.signal().map(|frozen| match frozen {
+                        true => "Unfreeze physics",
+                        false => "Freeze physics",
+                    }))
+                    .event(clone!(bevy_client, frozen => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client, frozen => async move {
+                            let freeze = !frozen.get();
+                            let result = configure_physics(&bevy_client, |config| {
+                                config.active = !freeze;
+                            }).await;
+                            if result.is_ok() {
+                                frozen.set_neq(freeze);
+                            }
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text("Shake")
+                    .event(clone!(bevy_client => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client => async move {
+                            let _ = bevy_main::send_command(&bevy_client, &shared::Shake { impulse: 0.05 }).await;
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text_signal(minimap.signal_ref(|minimap| match minimap {
+                        Minimap::Hidden => "Show minimap",
+                        _ => "Hide minimap",
+                    }))
+                    .event(clone!(bevy_client, minimap => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client, minimap => async move {
+                            let shown = minimap.get_cloned();
+                            let result = match shown {
+                                Minimap::Hidden => show_minimap(&bevy_client).await,
+                                Minimap::Viewport => {
+                                    let command = shared::Minimap { visible: false };
+                                    bevy_main::send_command(&bevy_client, &command).await
+                                        .map(|_| Minimap::Hidden)
+                                },
+                                Minimap::Canvas(..) => Ok(Minimap::Hidden),
+                            };
+                            match result {
+                                Ok(shown) => minimap.set(shown),
+                                Err(error) => web_sys::console::warn_1(&format!("could not toggle minimap: {}", error).into()),
+                            }
+                        }));
+                    }))
+                }),
+                html!("button", {
+                    .text("Reset")
+                    .event(clone!(bevy_client, objects, minimap, slow_motion, frozen => move |_: dominator::events::Click| {
+                        wasm_bindgen_futures::spawn_local(clone!(bevy_client, objects, minimap, slow_motion, frozen => async move {
+                            if bevy_client.reset().await.is_ok() {
+                                /* the app is rebuilt, so the minimap camera and the physics configuration are reset too */
+                                objects.lock_mut().clear();
+                                if let Minimap::Viewport = *minimap.lock_ref() {
+                                    minimap.set(Minimap::Hidden);
+                                }
+                                slow_motion.set_neq(false);
+                                frozen.set_neq(false);
+                            }
+                        }));
+                    }))
+                }),
+            ])
         }))
+        .child(diagnostics::overlay(diagnostics))
+        .child(logs::panel(logs, bevy_client))
+    })
+}
+
+/// How the top-down view of the arena is shown
+#[derive(Clone)]
+enum Minimap {
+    Hidden,
+    /// a second camera renders onto a corner of the primary canvas
+    Viewport,
+    /// the worker renders onto a separate canvas, which requires the WebGPU backend
+    Canvas(shared::WindowId, HtmlCanvasElement),
+}
+
+/// Shows the minimap on a separate canvas, falling back to a viewport on the primary canvas if
+/// the worker can not render onto more than one canvas
+async fn show_minimap(bevy_client: &shared::BevyClient) -> Result<Minimap, shared::BevyError> {
+    match bevy_main::canvas::attach(bevy_client).await {
+        Ok((window, canvas)) => Ok(Minimap::Canvas(window, canvas)),
+        Err(shared::BevyError::MultipleCanvasesUnsupported(_)) => {
+            bevy_main::send_command(bevy_client, &shared::Minimap { visible: true }).await?;
+            Ok(Minimap::Viewport)
+        },
+        Err(error) => Err(error),
+    }
+}
+
+/// Shows the health of the worker and a button for restarting it after a crash
+fn health_status(health: &health::Health) -> Dom {
+    html!("div", {
+        .style("position", "absolute")
+        .style("bottom", "0")
+        .style("left", "0")
+        .style("padding", "10px")
+        .visible_signal(health.signal().map(|health| health != health::WorkerHealth::Running))
+        .children(&mut [
+            html!("span", {
+                .text_signal(map_ref! {
+                    let health = health.signal(),
+                    let crash = health.crash_signal() => {
+                        let crash = match crash {
+                            Some(crash) => format!(": {}", crash),
+                            None => String::new(),
+                        };
+                        match health {
+                            health::WorkerHealth::Running => String::new(),
+                            health::WorkerHealth::Crashed => format!("The simulation crashed{}", crash),
+                            health::WorkerHealth::Restarting => format!("Restarting the simulation{}", crash),
+                        }
+                    }
+                })
+            }),
+            html!("button", {
+                .text("Restart")
+                .visible_signal(health.signal().map(|health| health == health::WorkerHealth::Crashed))
+                .event(clone!(health => move |_: dominator::events::Click| {
+                    health.restarting();
+                }))
+            }),
+        ])
+    })
+}
+
+/// Shows a message explaining why the simulation could not be started
+fn show_failure(container: &web_sys::HtmlElement, error: &shared::BevyError) {
+    let hint = match error {
+        shared::BevyError::WebGl2Unavailable =>
+            "This demo requires a browser with WebGL2 support inside of web workers.",
+        shared::BevyError::WebGpuUnavailable =>
+            "This build of the demo requires a browser with WebGPU support inside of web workers.",
+        _ => "Reload the page to try again.",
+    };
+    dominator::append_dom(container, html!("div", {
+        .style("padding", "10px")
+        .children(&mut [
+            html!("h2", {
+                .text(&format!("Could not start the simulation: {}", error))
+            }),
+            html!("p", {
+                .text(hint)
+            }),
+        ])
     }));
-    
-    /* register event handlers against the canvas */
-    let handlers = events::register(&canvas, &bevy_client);
-    /* do not drop the event handlers since this would cause them to be unregistered */
-    std::mem::forget(handlers);
+}
+
+/// Loads the arena from the JSON document referenced by the `arena` query parameter, e.g.,
+/// `?arena=arenas/hexagon.json`. Falls back to the default arena if the parameter is missing or
+/// the document could not be loaded.
+async fn arena() -> shared::ArenaDesc {
+    match query_param("arena") {
+        Some(url) => load_arena(&url).await.unwrap_or_else(|error| {
+            web_sys::console::warn_1(&format!("could not load arena: {}", error).into());
+            Default::default()
+        }),
+        None => Default::default(),
+    }
+}
+
+/// Returns the element that hosts the user interface and the canvas, i.e., the element whose id is
+/// given by the `container` query parameter or `bevy` by default. The canvas is sized to fill this
+/// element, so its size should be determined by the layout of the page.
+fn container() -> web_sys::HtmlElement {
+    let id = query_param("container").unwrap_or_else(|| String::from(DEFAULT_CONTAINER_ID));
+    web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .get_element_by_id(&id)
+        .and_then(|element| element.dyn_into().ok())
+        .unwrap_or_else(|| {
+            web_sys::console::warn_1(&format!("could not find container #{}, using the body", id).into());
+            dominator::body()
+        })
+}
+
+fn query_param(name: &str) -> Option<String> {
+    let search = web_sys::window()
+        .unwrap()
+        .location()
+        .search()
+        .unwrap_or_default();
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()
+        .and_then(|params| params.get(name))
+}
+
+async fn load_arena(url: &str) -> Result<shared::ArenaDesc, gloo_net::Error> {
+    let response = gloo_net::http::Request::get(url).send().await?;
+    if !response.ok() {
+        return Err(gloo_net::Error::GlooError(format!("{} {}", response.status(), response.status_text())));
+    }
+    response.json().await
+}
+
+/// Reads the current physics configuration from the worker, modifies it, and writes it back
+async fn configure_physics(
+    bevy_client: &shared::BevyClient,
+    modify: impl FnOnce(&mut shared::PhysicsConfig),
+) -> Result<(), shared::BevyError> {
+    let mut config = bevy_client.physics().await?;
+    modify(&mut config);
+    bevy_client.set_physics(config).await
+}
+
+/// Describes a ball with a random color that is dropped at a random location in the arena
+fn random_ball() -> shared::ObjectDesc {
+    const BALL_RADIUS: f32 = 0.075;
+    let random = || js_sys::Math::random() as f32;
+    shared::ObjectDesc {
+        shape: shared::Shape::Ball { radius: BALL_RADIUS },
+        mass: 0.1,
+        restitution: 1.0,
+        color: [random(), random(), random(), 1.0],
+        translation: Vec3::new(2.0 * random() - 1.0, 0.5, 2.0 * random() - 1.0),
+        rotation: Quat::IDENTITY,
+        draggable: true,
+    }
 }
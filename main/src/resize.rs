@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use futures_signals::signal::{Mutable, Signal, SignalExt};
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, ResizeObserver, ResizeObserverBoxOptions, ResizeObserverEntry, ResizeObserverOptions, ResizeObserverSize};
+
+/// How long the size of an element must remain unchanged before it is reported
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// The size of the content box of an element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+    /// the width in CSS pixels
+    pub width: u32,
+    /// the height in CSS pixels
+    pub height: u32,
+    /// the size in physical pixels if the browser supports `devicePixelContentBoxSize`
+    pub physical_size: Option<(u32, u32)>,
+}
+
+/// Observes the size of an element using a `ResizeObserver`. The observer is disconnected when
+/// this struct is dropped.
+pub struct SizeObserver {
+    observer: ResizeObserver,
+    size: Mutable<Option<Size>>,
+    _callback: Closure<dyn FnMut(js_sys::Array)>,
+}
+
+impl SizeObserver {
+    pub fn new(element: &Element) -> SizeObserver {
+        let size = Mutable::new(None);
+        let callback = Closure::new({
+            let size = size.clone();
+            move |entries: js_sys::Array| {
+                /* only the last entry is relevant since a single element is observed */
+                if let Some(entry) = entries.iter().last() {
+                    size.set_neq(Some(entry_size(entry.unchecked_ref())));
+                }
+            }
+        });
+        let observer = ResizeObserver::new(callback.as_ref().unchecked_ref())
+            .expect("could not create resize observer");
+        match supports_device_pixel_content_box() {
+            true => {
+                /* this box is also observed so that changes to the device pixel ratio are reported */
+                let options = ResizeObserverOptions::new();
+                options.set_box(ResizeObserverBoxOptions::DevicePixelContentBox);
+                observer.observe_with_options(element, &options);
+            },
+            false => observer.observe(element),
+        }
+        SizeObserver {
+            observer,
+            size,
+            _callback: callback,
+        }
+    }
+
+    /// Returns a signal of the size that only changes once the size has settled
+    pub fn signal(&self) -> impl Signal<Item = Option<Size>> {
+        /* the pending future is cancelled whenever the size changes again */
+        self.size.signal().map_future(|size| async move {
+            gloo_timers::future::sleep(DEBOUNCE).await;
+            size
+        }).map(Option::flatten)
+    }
+}
+
+impl Drop for SizeObserver {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
+fn entry_size(entry: &ResizeObserverEntry) -> Size {
+    let content_box_size = entry.content_box_size().get(0).unchecked_into::<ResizeObserverSize>();
+    /* this property is undefined in browsers that do not support it */
+    let physical_size = Some(entry.device_pixel_content_box_size())
+        .filter(|sizes| !sizes.is_undefined())
+        .map(|sizes| sizes.get(0).unchecked_into::<ResizeObserverSize>())
+        .map(|size| (size.inline_size() as u32, size.block_size() as u32));
+    Size {
+        width: content_box_size.inline_size().round() as u32,
+        height: content_box_size.block_size().round() as u32,
+        physical_size,
+    }
+}
+
+fn supports_device_pixel_content_box() -> bool {
+    js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("ResizeObserverEntry"))
+        .and_then(|entry| js_sys::Reflect::get(&entry, &JsValue::from_str("prototype")))
+        .and_then(|prototype| js_sys::Reflect::has(&prototype, &JsValue::from_str("devicePixelContentBoxSize")))
+        .unwrap_or(false)
+}
@@ -10,23 +10,27 @@ import os
 import signal
 import socketserver
 import subprocess
+import sys
 
 PORT = 3000
 
-# build project
+# build project, passing --webgpu renders using WebGPU instead of WebGL2
+backend = ["--no-default-features", "--features", "worker/webgpu"] if "--webgpu" in sys.argv else []
 subprocess.run([
     "cargo",
     "build",
     "--release"
-])
+] + backend)
 
-# create the output directory and symlink index.html and reset.css
+# create the output directory and symlink index.html, reset.css, and the arenas
 if not os.path.isdir('output'):
     os.mkdir('output')
 if not os.path.islink('output/index.html'):
     os.symlink('../index.html', 'output/index.html')
 if not os.path.islink('output/reset.css'):
     os.symlink('../reset.css', 'output/reset.css')
+if not os.path.islink('output/arenas'):
+    os.symlink('../arenas', 'output/arenas')
 
 # generate bindings
 print('Generating bindings')
@@ -79,6 +83,11 @@ with open('output/worker.js', 'w') as worker_js:
 class RequestHandler(http.server.SimpleHTTPRequestHandler):
     def __init__(self, *args, **kwargs):
         super().__init__(*args, directory='output', **kwargs)
+    # cross-origin isolation is required for SharedArrayBuffer, which is used for input events
+    def end_headers(self):
+        self.send_header('Cross-Origin-Opener-Policy', 'same-origin')
+        self.send_header('Cross-Origin-Embedder-Policy', 'require-corp')
+        super().end_headers()
 server = socketserver.TCPServer(('localhost', PORT), RequestHandler)
 signal.signal(signal.SIGINT,
     lambda _signal, _frame: setattr(server, '_BaseServer__shutdown_request', True))
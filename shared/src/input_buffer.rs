@@ -0,0 +1,196 @@
+use wasm_bindgen::JsValue;
+
+use crate::{BevyError, Event, TouchPhase, WindowId};
+
+/// the number of 32-bit words in the header, i.e., the write index followed by the read index
+const HEADER_WORDS: u32 = 2;
+const WRITE_INDEX: u32 = 0;
+const READ_INDEX: u32 = 1;
+/// the number of 32-bit words in a record, i.e., the kind, the window, and up to six fields
+const RECORD_WORDS: u32 = 8;
+
+const CURSOR_ENTERED: i32 = 1;
+const CURSOR_LEFT: i32 = 2;
+const CURSOR_MOVED: i32 = 3;
+const MOUSE_MOTION: i32 = 4;
+const MOUSE_WHEEL: i32 = 5;
+const MOUSE_BUTTON: i32 = 6;
+const TOUCH: i32 = 7;
+
+/// A single-producer, single-consumer ring buffer of input events in a `SharedArrayBuffer`. The
+/// main thread pushes the pointer and wheel events of a window into the buffer and the worker pops
+/// them at the start of each update, so these events neither wait for an RPC message nor for the
+/// lock on the app. Other events (e.g., keyboard input) can not be pushed and are sent using
+/// [`crate::Bevy::process_events`] instead.
+///
+/// Each record consists of [`RECORD_WORDS`] 32-bit words. The indices in the header are accessed
+/// using `Atomics`, a record is only written before the write index is advanced past it and only
+/// read before the read index is advanced past it.
+#[derive(Debug, Clone)]
+pub struct InputBuffer {
+    buffer: js_sys::SharedArrayBuffer,
+    header: js_sys::Int32Array,
+    records: js_sys::Int32Array,
+    capacity: u32,
+}
+
+impl InputBuffer {
+    /// Allocates a buffer for the given number of records. Returns `None` if `SharedArrayBuffer`
+    /// is unavailable, which is the case unless the page is cross-origin isolated.
+    pub fn new(capacity: u32) -> Option<InputBuffer> {
+        let global = js_sys::global();
+        let isolated = js_sys::Reflect::get(&global, &JsValue::from_str("crossOriginIsolated"))
+            .ok()
+            .and_then(|isolated| isolated.as_bool())
+            .unwrap_or(false);
+        if !isolated || capacity < 2 {
+            return None;
+        }
+        let length = (HEADER_WORDS + capacity * RECORD_WORDS) * 4;
+        InputBuffer::from_buffer(js_sys::SharedArrayBuffer::new(length)).ok()
+    }
+
+    /// Wraps a buffer that was allocated using [`InputBuffer::new`], e.g., after it has been
+    /// posted to the worker. Returns an error if the buffer can not hold the header and at least
+    /// two records, since one record is always left empty.
+    pub fn from_buffer(buffer: js_sys::SharedArrayBuffer) -> Result<InputBuffer, BevyError> {
+        let length = buffer.byte_length();
+        if length < (HEADER_WORDS + 2 * RECORD_WORDS) * 4 {
+            return Err(BevyError::InputBufferTooSmall(length));
+        }
+        let capacity = (length / 4 - HEADER_WORDS) / RECORD_WORDS;
+        let header = js_sys::Int32Array::new_with_byte_offset_and_length(&buffer, 0, HEADER_WORDS);
+        let records = js_sys::Int32Array::new_with_byte_offset_and_length(
+            &buffer,
+            HEADER_WORDS * 4,
+            capacity * RECORD_WORDS,
+        );
+        Ok(InputBuffer { buffer, header, records, capacity })
+    }
+
+    pub fn buffer(&self) -> &js_sys::SharedArrayBuffer {
+        &self.buffer
+    }
+
+    /// Writes an event into the buffer. Returns `false` if the buffer is full or if the event can
+    /// not be represented as a record.
+    pub fn push(&self, window: WindowId, event: &Event) -> bool {
+        let Some(record) = encode(window, event) else {
+            return false;
+        };
+        let write = self.load(WRITE_INDEX);
+        let next = (write + 1) % self.capacity;
+        /* one record is always left empty to distinguish a full buffer from an empty one */
+        if next == self.load(READ_INDEX) {
+            return false;
+        }
+        for (offset, word) in record.into_iter().enumerate() {
+            self.records.set_index(write * RECORD_WORDS + offset as u32, word);
+        }
+        self.store(WRITE_INDEX, next);
+        true
+    }
+
+    /// Reads the oldest event from the buffer, records that can not be decoded are skipped
+    pub fn pop(&self) -> Option<(WindowId, Event)> {
+        loop {
+            let read = self.load(READ_INDEX);
+            if read == self.load(WRITE_INDEX) {
+                return None;
+            }
+            let mut record = [0; RECORD_WORDS as usize];
+            for (offset, word) in record.iter_mut().enumerate() {
+                *word = self.records.get_index(read * RECORD_WORDS + offset as u32);
+            }
+            self.store(READ_INDEX, (read + 1) % self.capacity);
+            if let Some(event) = decode(&record) {
+                return Some(event);
+            }
+        }
+    }
+
+    fn load(&self, index: u32) -> u32 {
+        js_sys::Atomics::load(&self.header, index).unwrap() as u32
+    }
+
+    fn store(&self, index: u32, value: u32) {
+        js_sys::Atomics::store(&self.header, index, value as i32).unwrap();
+    }
+}
+
+fn encode(WindowId(window): WindowId, event: &Event) -> Option<[i32; RECORD_WORDS as usize]> {
+    /* window ids are allocated sequentially, so they fit into a single word */
+    let window = i32::try_from(window).ok()?;
+    let fields = match *event {
+        Event::CursorEntered => [CURSOR_ENTERED, 0, 0, 0, 0, 0, 0],
+        Event::CursorLeft => [CURSOR_LEFT, 0, 0, 0, 0, 0, 0],
+        Event::CursorMoved { delta, position: (x, y) } => {
+            let (delta_x, delta_y) = delta.unwrap_or_default();
+            [CURSOR_MOVED, delta.is_some() as i32, delta_x, delta_y, x, y, 0]
+        },
+        Event::MouseMotion { delta: (delta_x, delta_y) } =>
+            [MOUSE_MOTION, delta_x, delta_y, 0, 0, 0, 0],
+        Event::MouseWheel { delta: (delta_x, delta_y), unit } => [
+            MOUSE_WHEEL,
+            (delta_x as f32).to_bits() as i32,
+            (delta_y as f32).to_bits() as i32,
+            unit as i32,
+            0, 0, 0,
+        ],
+        Event::MouseButton { pressed, button } =>
+            [MOUSE_BUTTON, pressed as i32, button as i32, 0, 0, 0, 0],
+        Event::Touch { phase, id, position: (x, y), force } => [
+            TOUCH,
+            match phase {
+                TouchPhase::Started => 0,
+                TouchPhase::Moved => 1,
+                TouchPhase::Ended => 2,
+                TouchPhase::Canceled => 3,
+            },
+            id,
+            x,
+            y,
+            force.is_some() as i32,
+            (force.unwrap_or_default() as f32).to_bits() as i32,
+        ],
+        Event::Resize { .. } |
+        Event::ScaleFactorChanged { .. } |
+        Event::PointerLockChanged { .. } |
+        Event::KeyboardInput { .. } => return None,
+    };
+    let [kind, a, b, c, d, e, f] = fields;
+    Some([kind, window, a, b, c, d, e, f])
+}
+
+fn decode(record: &[i32; RECORD_WORDS as usize]) -> Option<(WindowId, Event)> {
+    let [kind, window, a, b, c, d, e, f] = *record;
+    let window = WindowId(u32::try_from(window).ok()? as u64);
+    let event = match kind {
+        CURSOR_ENTERED => Event::CursorEntered,
+        CURSOR_LEFT => Event::CursorLeft,
+        CURSOR_MOVED => Event::CursorMoved {
+            delta: (a != 0).then_some((b, c)),
+            position: (d, e),
+        },
+        MOUSE_MOTION => Event::MouseMotion { delta: (a, b) },
+        MOUSE_WHEEL => Event::MouseWheel {
+            delta: (f32::from_bits(a as u32) as f64, f32::from_bits(b as u32) as f64),
+            unit: c as u32,
+        },
+        MOUSE_BUTTON => Event::MouseButton { pressed: a != 0, button: b as i16 },
+        TOUCH => Event::Touch {
+            phase: match a {
+                0 => TouchPhase::Started,
+                1 => TouchPhase::Moved,
+                2 => TouchPhase::Ended,
+                3 => TouchPhase::Canceled,
+                _ => return None,
+            },
+            id: b,
+            position: (c, d),
+            force: (e != 0).then_some(f32::from_bits(f as u32) as f64),
+        },
+        _ => return None,
+    };
+    Some((window, event))
+}
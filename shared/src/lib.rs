@@ -1,26 +1,372 @@
 use std::time::Duration;
 
+use glam::{Quat, Vec2, Vec3};
+
+mod input_buffer;
+
+pub use input_buffer::InputBuffer;
+
 #[web_rpc::service]
 pub trait Bevy {
     #[post(transfer(canvas))]
     async fn init(
         canvas: web_sys::OffscreenCanvas,
-    ) -> Result<(), ()>;
+        arena: ArenaDesc,
+    ) -> Result<(), BevyError>;
+
+    /// Adds a window that renders onto the given canvas, the app is notified by a `WindowCreated`
+    /// event and is expected to spawn a camera for it. The returned id is used to target the
+    /// window in [`Bevy::process_event`].
+    #[post(transfer(canvas))]
+    async fn attach_canvas(
+        canvas: web_sys::OffscreenCanvas,
+    ) -> Result<WindowId, BevyError>;
+
+    /// Closes a window and releases its rendering surface, the canvas of the window can no longer
+    /// be used afterwards. The update loop continues to run if the primary window is detached.
+    async fn detach_canvas(
+        window: WindowId,
+    ) -> Result<(), BevyError>;
+
+    async fn start(pacing: Pacing) -> Result<(), BevyError>;
+
+    async fn pause() -> Result<(), BevyError>;
+
+    async fn resume() -> Result<(), BevyError>;
+
+    /// Stops the update loop and returns the number of frames that were rendered
+    async fn stop() -> Result<u32, BevyError>;
+
+    /// Rebuilds the app on a fresh world and runs the setup again. The windows (with their ids),
+    /// the input buffer, and the diagnostics subscription are carried over, while everything else
+    /// (e.g., the objects, the physics configuration, and the time) starts from scratch. The ids
+    /// of the previous objects are not reused. If the app can not be rebuilt, the worker must be
+    /// restarted.
+    async fn reset() -> Result<(), BevyError>;
+
+    /// Runs the given number of updates (and physics steps) while paused and returns the
+    /// resulting frame count
+    async fn step(frames: u32) -> Result<u32, BevyError>;
+
+    async fn set_physics(
+        config: PhysicsConfig,
+    ) -> Result<(), BevyError>;
 
-    async fn start(interval: Duration);
-    
-    async fn stop();
+    async fn physics() -> Result<PhysicsConfig, BevyError>;
+
+    /// Pushes a [`DiagnosticsSnapshot`] to the main thread at the given interval. Passing `None`
+    /// cancels the subscription.
+    async fn subscribe_diagnostics(
+        interval: Option<Duration>,
+    ) -> Result<(), BevyError>;
+
+    /// Replaces the filter that selects which log records are written to the console and
+    /// forwarded to the main thread, e.g., `worker=debug,bevy_render=warn`
+    async fn set_log_filter(
+        filter: String,
+    ) -> Result<(), BevyError>;
 
     async fn process_event(
+        window: WindowId,
         event: Event,
-    );
+    ) -> Result<(), BevyError>;
+
+    /// Processes several events in order, e.g., the input events of an animation frame
+    async fn process_events(
+        window: WindowId,
+        events: Vec<Event>,
+    ) -> Result<(), BevyError>;
+
+    /// Shares a buffer into which the main thread writes the pointer and wheel events of its
+    /// windows, the worker drains the buffer at the start of each update. The buffer is posted
+    /// rather than transferred so that both threads can access it.
+    #[post(buffer)]
+    async fn attach_input_buffer(
+        buffer: js_sys::SharedArrayBuffer,
+    ) -> Result<(), BevyError>;
+
+    async fn spawn_object(
+        object: ObjectDesc,
+    ) -> Result<ObjectId, BevyError>;
+
+    async fn despawn(
+        object: ObjectId,
+    ) -> Result<(), BevyError>;
+
+    /// Sends an app-specific command to the worker, the payload is the JSON representation of the
+    /// [`RpcCommand`] whose tag is given
+    async fn send_command(
+        tag: String,
+        payload: String,
+    ) -> Result<(), BevyError>;
+}
+
+/// Errors that are returned by the methods of the [`Bevy`] service
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum BevyError {
+    /// the worker could not be started, e.g., because its script could not be loaded
+    WorkerFailed(String),
+    /// the plugins of the app could not be initialised
+    PluginInit(String),
+    /// a WebGL2 context could not be created, the browser or device does not support it
+    WebGl2Unavailable,
+    /// the worker was built with the WebGPU backend, but the browser does not support it
+    WebGpuUnavailable,
+    /// the app does not have a primary window
+    NoPrimaryWindow,
+    /// the event could not be converted into a Bevy event
+    InvalidEvent(String),
+    /// the app has not been initialised
+    NotInitialised,
+    /// the app has already been initialised
+    AlreadyInitialised,
+    /// a previous initialisation failed after the plugins were added, the worker must be restarted
+    InitFailed,
+    /// the update loop is already running (or paused)
+    AlreadyRunning,
+    /// the update loop is not running
+    NotRunning,
+    /// the update loop is not paused
+    NotPaused,
+    /// the physics configuration is invalid, e.g., it has a negative time scale
+    InvalidPhysicsConfig,
+    /// the arena description is invalid, e.g., its boundary has fewer than three corners
+    InvalidArena(String),
+    /// the object description is invalid, e.g., its shape has a negative radius
+    InvalidObject(String),
+    /// there is no object with the given id
+    ObjectNotFound(ObjectId),
+    /// the log filter could not be parsed
+    InvalidLogFilter(String),
+    /// there is no window with the given id
+    WindowNotFound(WindowId),
+    /// the rendering backend (e.g., WebGL2) can only render onto a single canvas
+    MultipleCanvasesUnsupported(String),
+    /// the shared input buffer can not hold the header and two records, its length is given in bytes
+    InputBufferTooSmall(u32),
+    /// the control of a canvas could not be transferred to an offscreen canvas
+    CanvasTransferFailed(String),
+    /// no command has been registered with the given tag
+    UnknownCommand(String),
+    /// the payload of the command could not be serialized or deserialized
+    InvalidCommand(String),
+}
+
+impl std::fmt::Display for BevyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BevyError::WorkerFailed(reason) => write!(f, "could not start the worker: {}", reason),
+            BevyError::PluginInit(reason) => write!(f, "could not initialise plugins: {}", reason),
+            BevyError::WebGl2Unavailable => write!(f, "WebGL2 is not available in this browser"),
+            BevyError::WebGpuUnavailable => write!(f, "WebGPU is not available in this browser"),
+            BevyError::NoPrimaryWindow => write!(f, "there is no primary window"),
+            BevyError::InvalidEvent(reason) => write!(f, "invalid event: {}", reason),
+            BevyError::NotInitialised => write!(f, "the app has not been initialised"),
+            BevyError::AlreadyInitialised => write!(f, "the app has already been initialised"),
+            BevyError::InitFailed => write!(f, "a previous initialisation failed, the worker must be restarted"),
+            BevyError::AlreadyRunning => write!(f, "the app is already running"),
+            BevyError::NotRunning => write!(f, "the app is not running"),
+            BevyError::NotPaused => write!(f, "the app is not paused"),
+            BevyError::InvalidPhysicsConfig => write!(f, "invalid physics configuration"),
+            BevyError::InvalidArena(reason) => write!(f, "invalid arena: {}", reason),
+            BevyError::InvalidObject(reason) => write!(f, "invalid object: {}", reason),
+            BevyError::ObjectNotFound(ObjectId(id)) => write!(f, "object {} not found", id),
+            BevyError::InvalidLogFilter(reason) => write!(f, "invalid log filter: {}", reason),
+            BevyError::WindowNotFound(WindowId(id)) => write!(f, "window {} not found", id),
+            BevyError::MultipleCanvasesUnsupported(backend) =>
+                write!(f, "the {} backend can not render onto more than one canvas", backend),
+            BevyError::InputBufferTooSmall(length) => write!(f, "the input buffer of {} bytes is too small", length),
+            BevyError::CanvasTransferFailed(reason) => write!(f, "could not transfer canvas: {}", reason),
+            BevyError::UnknownCommand(tag) => write!(f, "unknown command {}", tag),
+            BevyError::InvalidCommand(reason) => write!(f, "invalid command: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for BevyError {}
+
+/// A command that is defined by an app and sent to the worker using [`Bevy::send_command`]. The
+/// worker deserializes the command and emits it as an event if its type has been registered.
+pub trait RpcCommand: serde::Serialize + serde::de::DeserializeOwned {
+    /// identifies the type of the command, each type must have a unique tag
+    const TAG: &'static str;
+}
+
+/// Applies an impulse in a random horizontal direction to each of the objects
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Shake {
+    pub impulse: f32,
+}
+
+impl RpcCommand for Shake {
+    const TAG: &'static str = "shake";
+}
+
+/// Shows or hides a top-down view of the arena in a corner of the primary window
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Minimap {
+    pub visible: bool,
+}
+
+impl RpcCommand for Minimap {
+    const TAG: &'static str = "minimap";
+}
+
+/// Determines when the worker updates the app
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Pacing {
+    /// update the app at a fixed interval
+    Interval(Duration),
+    /// update the app once per display refresh using `requestAnimationFrame`, falls back to a
+    /// fixed interval if the browser does not provide animation frames in workers
+    AnimationFrame,
+}
+
+/// Configuration of the physics simulation
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct PhysicsConfig {
+    pub timestep: Timestep,
+    pub substeps: usize,
+    /// the rate at which simulated time passes, e.g., 0.5 for slow motion or 2.0 for fast-forward
+    pub time_scale: f32,
+    /// whether the simulation is stepped, the world continues to be rendered while inactive
+    pub active: bool,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            timestep: Timestep::Fixed { dt: 0.05 }, // 20 fps
+            substeps: 20,
+            time_scale: 1.0,
+            active: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Timestep {
+    /// advance the simulation by `dt` seconds on every update
+    Fixed {
+        dt: f32,
+    },
+    /// advance the simulation by the elapsed time, but by no more than `max_dt` seconds
+    Variable {
+        max_dt: f32,
+    },
+    /// advance the simulation in steps of `dt` seconds to keep up with the elapsed time
+    Interpolated {
+        dt: f32,
+    },
+}
+
+/// Service implemented on the main thread so that the worker can notify the user interface
+#[web_rpc::service]
+pub trait Main {
+    fn notify(notification: Notification);
+
+    /// Reports a panic in the worker, the worker should be considered dead after this call
+    fn panic(message: String, location: Option<String>);
+
+    fn log(record: LogRecord);
+
+    fn diagnostics(snapshot: DiagnosticsSnapshot);
+
+    /// Sets the cursor that is shown over the canvas of the primary window, this is called when
+    /// the cursor of the primary window changes in the worker
+    fn set_cursor(cursor: Cursor);
+}
+
+/// The cursor of a window, i.e., `Window::cursor` in the worker
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Cursor {
+    /// the value of the CSS `cursor` property that corresponds to the icon, e.g., `grab`
+    pub icon: String,
+    pub visible: bool,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor {
+            icon: String::from("default"),
+            visible: true,
+        }
+    }
+}
+
+/// The smoothed values of the diagnostics, the values are `None` until they have been measured
+#[derive(Debug, Default, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct DiagnosticsSnapshot {
+    pub fps: Option<f64>,
+    /// the time between frames in milliseconds
+    pub frame_time: Option<f64>,
+    pub entity_count: Option<f64>,
+    /// the time spent stepping the physics simulation in milliseconds
+    pub physics_step_time: Option<f64>,
+}
+
+/// The log filter that is used by the worker until it is replaced using [`Bevy::set_log_filter`],
+/// it enables the logs of the library (`bevy_worker`) and of the demo (`worker`)
+pub const DEFAULT_LOG_FILTER: &str = "bevy_worker=trace,worker=trace,shared=trace";
+
+/// A log record that was emitted in the worker
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    /// the module path (or custom target) that emitted the record
+    pub target: String,
+    /// the message followed by any other fields of the record
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum Notification {
+    /// the world has been set up and the first frame has been rendered
+    Loaded,
+    /// an object that was spawned using [`Bevy::spawn_object`] (or during setup) is being dragged
+    DragStarted {
+        object: ObjectId,
+    },
+    DragEnded {
+        object: ObjectId,
+    },
+    /// an object started or stopped touching another object or the arena, which has no id
+    Collision {
+        started: bool,
+        objects: (Option<ObjectId>, Option<ObjectId>),
+    },
+}
+
+/// Identifies a window in the worker, each window renders onto its own canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct WindowId(pub u64);
+
+impl WindowId {
+    /// the window that renders onto the canvas that was passed to [`Bevy::init`]
+    pub const PRIMARY: WindowId = WindowId(0);
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum Event {
+    /// the size of the canvas in CSS pixels
     Resize {
         width: u32,
-        height: u32
+        height: u32,
+        /// the exact size of the canvas in physical pixels (if known)
+        physical_size: Option<(u32, u32)>,
+    },
+    /// the ratio between physical pixels and CSS pixels, i.e., `devicePixelRatio`
+    ScaleFactorChanged {
+        scale_factor: f64,
     },
     CursorEntered,
     CursorLeft,
@@ -31,6 +377,10 @@ pub enum Event {
     MouseMotion {
         delta: (i32, i32)
     },
+    /// whether the pointer is locked to the canvas, i.e., `document.pointerLockElement`
+    PointerLockChanged {
+        locked: bool,
+    },
     MouseWheel {
         delta: (f64, f64),
         unit: u32
@@ -39,4 +389,144 @@ pub enum Event {
         pressed: bool,
         button: i16,
     },
-}
\ No newline at end of file
+    KeyboardInput {
+        pressed: bool,
+        /// the physical key as reported by `KeyboardEvent.code`
+        code: String,
+        /// the logical key as reported by `KeyboardEvent.key`
+        key: String,
+        repeat: bool,
+        modifiers: Modifiers,
+    },
+    Touch {
+        phase: TouchPhase,
+        /// the `pointerId` of the pointer event, unique for each finger
+        id: i32,
+        position: (i32, i32),
+        /// the normalized pressure of the touch (between 0.0 and 1.0)
+        force: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Canceled,
+}
+
+#[derive(Debug, Default, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+/// Stable identifier for an object that was spawned in the worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ObjectId(pub u64);
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ObjectDesc {
+    pub shape: Shape,
+    pub mass: f32,
+    pub restitution: f32,
+    /// the color of the object as sRGBA
+    pub color: [f32; 4],
+    pub translation: Vec3,
+    pub rotation: Quat,
+    /// whether the object can be dragged around using the pointer
+    pub draggable: bool,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Shape {
+    Ball {
+        radius: f32,
+    },
+    Cuboid {
+        size: Vec3,
+    },
+    Cylinder {
+        radius: f32,
+        height: f32,
+    },
+}
+
+/// Describes the static part of the world, i.e., the floor, the walls, and any obstacles
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ArenaDesc {
+    /// the size of the arena along the x and z axes
+    pub size: Vec2,
+    pub wall: WallDesc,
+    pub floor: FloorDesc,
+    /// the corners of a polygonal boundary on the xz-plane, walls are placed along the centerline
+    /// between consecutive corners. If this is `None`, the walls are placed along the edges of the
+    /// arena.
+    pub boundary: Option<Vec<Vec2>>,
+    pub obstacles: Vec<ObstacleDesc>,
+}
+
+impl Default for ArenaDesc {
+    fn default() -> Self {
+        ArenaDesc {
+            size: Vec2::new(2.5, 2.5),
+            wall: WallDesc::default(),
+            floor: FloorDesc::default(),
+            boundary: None,
+            obstacles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct WallDesc {
+    pub height: f32,
+    pub thickness: f32,
+    /// the color of the walls as sRGBA
+    pub color: [f32; 4],
+}
+
+impl Default for WallDesc {
+    fn default() -> Self {
+        WallDesc {
+            height: 0.075,
+            thickness: 0.075,
+            color: [0.7, 0.7, 0.7, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FloorDesc {
+    /// the color of the floor as sRGBA
+    pub color: [f32; 4],
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+}
+
+impl Default for FloorDesc {
+    fn default() -> Self {
+        FloorDesc {
+            color: [0.9, 0.9, 0.9, 1.0],
+            perceptual_roughness: 0.5,
+            metallic: 0.0,
+        }
+    }
+}
+
+/// A static object inside of the arena
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ObstacleDesc {
+    pub shape: Shape,
+    /// the color of the obstacle as sRGBA
+    pub color: [f32; 4],
+    pub translation: Vec3,
+    #[serde(default)]
+    pub rotation: Quat,
+}
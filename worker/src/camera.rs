@@ -77,10 +77,15 @@ pub fn accumulate_mouse_events_system(
 }
 
 /// Pan the camera with middle mouse click, zoom with scroll wheel, orbit with right mouse click.
+/// Touch gestures are accumulated by [`crate::gesture::accumulate_touch_events_system`].
 pub fn update_camera_system(
     windows: Query<&Window, With<PrimaryWindow>>,
     mut query: Query<(&mut PanOrbitCamera, &mut Transform, &Projection)>,
 ) {
+    /* the primary window may have been detached */
+    let Some(window) = get_primary_window_size(&windows) else {
+        return;
+    };
     for (mut camera, mut transform, projection) in query.iter_mut() {
         if camera.orbit_button_changed {
             // only check for upside down when orbiting started or ended this frame
@@ -97,7 +102,6 @@ pub fn update_camera_system(
             let rotation_move = camera.rotation_move * LERP;
             camera.rotation_move -= rotation_move;
 
-            let window = get_primary_window_size(&windows);
             let delta_x = {
                 let delta = rotation_move.x / window.x * std::f32::consts::PI * 2.0;
                 if camera.upside_down { -delta } else { delta }
@@ -114,7 +118,6 @@ pub fn update_camera_system(
             let mut pan = camera.pan * LERP;
             camera.pan -= pan;
             // make panning distance independent of resolution and FOV,
-            let window = get_primary_window_size(&windows);
             if let Projection::Perspective(projection) = projection {
                 pan *= Vec2::new(projection.fov * projection.aspect_ratio, projection.fov) / window;
             }
@@ -150,7 +153,7 @@ pub fn update_camera_system(
     
 }
 
-fn get_primary_window_size(windows: &Query<&Window, With<PrimaryWindow>>) -> Vec2 {
-    let window = windows.get_single().unwrap();
-    Vec2::new(window.width(), window.height())
+fn get_primary_window_size(windows: &Query<&Window, With<PrimaryWindow>>) -> Option<Vec2> {
+    let window = windows.get_single().ok()?;
+    Some(Vec2::new(window.width(), window.height()))
 }
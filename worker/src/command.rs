@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use bevy_app::App;
+use bevy_ecs::{event::Event, system::Resource, world::World};
+use shared::{BevyError, RpcCommand};
+
+/// An app-specific command that was received from the main thread
+#[derive(Event, Debug, Clone)]
+pub struct RpcCommandEvent<T>(pub T);
+
+/// Deserializes the payload of a command and sends it as an event
+type Sender = fn(&mut World, &str) -> Result<(), BevyError>;
+
+/// Maps the tags of the registered commands to their senders
+#[derive(Resource, Default)]
+struct RpcCommands {
+    senders: HashMap<&'static str, Sender>,
+}
+
+pub trait AddRpcCommand {
+    /// Registers a command so that it is sent as an [`RpcCommandEvent`] when it is received from
+    /// the main thread. Registering a command replaces any command with the same tag.
+    fn add_rpc_command<T: RpcCommand + Send + Sync + 'static>(&mut self) -> &mut Self;
+}
+
+impl AddRpcCommand for App {
+    fn add_rpc_command<T: RpcCommand + Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.add_event::<RpcCommandEvent<T>>();
+        self.world
+            .get_resource_or_insert_with(RpcCommands::default)
+            .senders
+            .insert(T::TAG, send::<T>);
+        self
+    }
+}
+
+fn send<T: RpcCommand + Send + Sync + 'static>(world: &mut World, payload: &str) -> Result<(), BevyError> {
+    let command = serde_json::from_str::<T>(payload)
+        .map_err(|error| BevyError::InvalidCommand(error.to_string()))?;
+    world.send_event(RpcCommandEvent(command));
+    Ok(())
+}
+
+/// Sends the command with the given tag as an event
+pub(crate) fn send_command(world: &mut World, tag: &str, payload: &str) -> Result<(), BevyError> {
+    let send = world.get_resource::<RpcCommands>()
+        .and_then(|commands| commands.senders.get(tag).copied())
+        .ok_or_else(|| BevyError::UnknownCommand(tag.to_owned()))?;
+    send(world, payload)
+}
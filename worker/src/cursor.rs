@@ -0,0 +1,65 @@
+use bevy_ecs::{query::{Changed, With}, system::{Local, Query, Res}};
+use bevy_window::{CursorIcon, PrimaryWindow, Window};
+
+use crate::notify::MainClient;
+
+/// Sends the cursor of the primary window to the main thread whenever its icon or visibility
+/// changes, since the worker can not set the cursor over the canvas itself
+pub(crate) fn forward_cursor_system(
+    windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    main_client: Res<MainClient>,
+    mut forwarded: Local<Option<shared::Cursor>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let cursor = shared::Cursor {
+        icon: String::from(css_cursor(window.cursor.icon)),
+        visible: window.cursor.visible,
+    };
+    /* the window also changes when, e.g., it is resized, so only actual changes are forwarded */
+    if forwarded.as_ref() != Some(&cursor) {
+        main_client.get().set_cursor(cursor.clone());
+        *forwarded = Some(cursor);
+    }
+}
+
+/// Returns the value of the CSS `cursor` property that corresponds to an icon
+fn css_cursor(icon: CursorIcon) -> &'static str {
+    match icon {
+        CursorIcon::Default => "default",
+        CursorIcon::ContextMenu => "context-menu",
+        CursorIcon::Help => "help",
+        CursorIcon::Pointer => "pointer",
+        CursorIcon::Progress => "progress",
+        CursorIcon::Wait => "wait",
+        CursorIcon::Cell => "cell",
+        CursorIcon::Crosshair => "crosshair",
+        CursorIcon::Text => "text",
+        CursorIcon::VerticalText => "vertical-text",
+        CursorIcon::Alias => "alias",
+        CursorIcon::Copy => "copy",
+        CursorIcon::Move => "move",
+        CursorIcon::NoDrop => "no-drop",
+        CursorIcon::NotAllowed => "not-allowed",
+        CursorIcon::Grab => "grab",
+        CursorIcon::Grabbing => "grabbing",
+        CursorIcon::EResize => "e-resize",
+        CursorIcon::NResize => "n-resize",
+        CursorIcon::NeResize => "ne-resize",
+        CursorIcon::NwResize => "nw-resize",
+        CursorIcon::SResize => "s-resize",
+        CursorIcon::SeResize => "se-resize",
+        CursorIcon::SwResize => "sw-resize",
+        CursorIcon::WResize => "w-resize",
+        CursorIcon::EwResize => "ew-resize",
+        CursorIcon::NsResize => "ns-resize",
+        CursorIcon::NeswResize => "nesw-resize",
+        CursorIcon::NwseResize => "nwse-resize",
+        CursorIcon::ColResize => "col-resize",
+        CursorIcon::RowResize => "row-resize",
+        CursorIcon::AllScroll => "all-scroll",
+        CursorIcon::ZoomIn => "zoom-in",
+        CursorIcon::ZoomOut => "zoom-out",
+    }
+}
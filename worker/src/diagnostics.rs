@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use bevy_app::{App, Last, Plugin, PostUpdate};
+use bevy_diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, RegisterDiagnostic};
+use bevy_ecs::{schedule::IntoSystemConfigs, system::{Res, ResMut, Resource}};
+use bevy_rapier3d::plugin::PhysicsSet;
+use bevy_time::{Real, Time, Timer, TimerMode};
+use bevy_utils::Instant;
+use shared::DiagnosticsSnapshot;
+
+use crate::notify::MainClient;
+
+/// The time spent stepping the physics simulation in milliseconds
+pub const PHYSICS_STEP_TIME: DiagnosticPath = DiagnosticPath::const_new("physics_step_time");
+
+/// Measures the frame time, the entity count, and the time spent stepping the physics and pushes
+/// them to the main thread while a subscription is active
+pub struct DiagnosticsStreamPlugin;
+
+impl Plugin for DiagnosticsStreamPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(FrameTimeDiagnosticsPlugin)
+            .add_plugins(EntityCountDiagnosticsPlugin)
+            .register_diagnostic(Diagnostic::new(PHYSICS_STEP_TIME).with_suffix("ms"))
+            .init_resource::<PhysicsStepStart>()
+            .init_resource::<Subscription>()
+            .add_systems(PostUpdate, (
+                physics_step_start_system
+                    .after(PhysicsSet::SyncBackend)
+                    .before(PhysicsSet::StepSimulation),
+                physics_step_end_system
+                    .after(PhysicsSet::StepSimulation)
+                    .before(PhysicsSet::Writeback),
+            ))
+            .add_systems(Last, stream_diagnostics_system);
+    }
+}
+
+/// The interval at which snapshots are pushed to the main thread (if subscribed)
+#[derive(Resource, Default)]
+pub struct Subscription(pub Option<Timer>);
+
+impl Subscription {
+    pub fn new(interval: Option<Duration>) -> Subscription {
+        Subscription(interval.map(|interval| Timer::new(interval, TimerMode::Repeating)))
+    }
+}
+
+#[derive(Resource, Default)]
+struct PhysicsStepStart(Option<Instant>);
+
+fn physics_step_start_system(mut start: ResMut<PhysicsStepStart>) {
+    start.0 = Some(Instant::now());
+}
+
+fn physics_step_end_system(
+    mut start: ResMut<PhysicsStepStart>,
+    mut diagnostics: Diagnostics,
+) {
+    if let Some(start) = start.0.take() {
+        diagnostics.add_measurement(&PHYSICS_STEP_TIME, || start.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+fn stream_diagnostics_system(
+    mut subscription: ResMut<Subscription>,
+    time: Res<Time<Real>>,
+    diagnostics: Res<DiagnosticsStore>,
+    main_client: Res<MainClient>,
+) {
+    let Some(timer) = subscription.0.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let smoothed = |path| diagnostics.get(path).and_then(Diagnostic::smoothed);
+    main_client.get().diagnostics(DiagnosticsSnapshot {
+        fps: smoothed(&FrameTimeDiagnosticsPlugin::FPS),
+        frame_time: smoothed(&FrameTimeDiagnosticsPlugin::FRAME_TIME),
+        entity_count: smoothed(&EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+        physics_step_time: smoothed(&PHYSICS_STEP_TIME),
+    });
+}
@@ -2,14 +2,15 @@ use bevy_ecs::{
     bundle::Bundle,
     component::Component,
     entity::Entity,
-    event::EventReader,
+    event::{EventReader, EventWriter},
     query::With,
-    system::{Commands, Query}
+    system::{Commands, Local, Query, Res}
 };
 use bevy_eventlistener::{callbacks::Listener, event_listener::{EntityEvent, On}};
 use bevy_math::{Vec2, Vec3};
 use bevy_render::camera::Camera;
 use bevy_transform::components::GlobalTransform;
+use bevy_window::{CursorIcon, PrimaryWindow, Window};
 use bevy_mod_picking::{
     events::{Drag, DragEnd, DragStart, Pointer},
     focus::PickingInteraction,
@@ -17,6 +18,9 @@ use bevy_mod_picking::{
     pointer::PointerButton
 };
 use bevy_rapier3d::dynamics::ExternalImpulse;
+use shared::Notification;
+
+use crate::{notify::Notify, world::Objects};
 
 #[derive(Component)]
 pub struct Target {
@@ -33,8 +37,13 @@ pub struct Target {
     pub distance: Vec2,
 }
 
+/// Marks an entity that can be dragged around using the pointer
+#[derive(Component, Default)]
+pub struct Draggable;
+
 #[derive(Bundle)]
 pub struct DraggableBundle {
+    draggable: Draggable,
     drag_start: On::<Pointer<DragStart>>,
     drag_end: On::<Pointer<DragEnd>>,
     external_impulse: ExternalImpulse,
@@ -45,6 +54,7 @@ pub struct DraggableBundle {
 impl Default for DraggableBundle {
     fn default() -> Self {
         Self {
+            draggable: Draggable,
             drag_start: On::<Pointer<DragStart>>::run(drag_start_system),
             drag_end: On::<Pointer<DragEnd>>::run(drag_end_system),
             external_impulse: Default::default(),
@@ -57,10 +67,15 @@ impl Default for DraggableBundle {
 fn drag_start_system(
     listener: Listener<Pointer<DragStart>>,
     target: Query<&GlobalTransform, With<ExternalImpulse>>,
-    mut commands: Commands
+    mut commands: Commands,
+    mut notifications: EventWriter<Notify>,
+    objects: Res<Objects>,
 ) {
     if listener.button == PointerButton::Primary {
         if let Ok(target_transform) = target.get(listener.target()) {
+            if let Some(object) = objects.id(listener.target()) {
+                notifications.send(Notify(Notification::DragStarted { object }));
+            }
             let position = listener.hit.position
                 .expect("backend does not support `position`");
             commands.entity(listener.target()).insert(Target {
@@ -75,8 +90,16 @@ fn drag_start_system(
 
 fn drag_end_system(
     listener: Listener<Pointer<DragEnd>>,
-    mut commands: Commands
+    target: Query<(), With<Target>>,
+    mut commands: Commands,
+    mut notifications: EventWriter<Notify>,
+    objects: Res<Objects>,
 ) {
+    if target.contains(listener.target()) {
+        if let Some(object) = objects.id(listener.target()) {
+            notifications.send(Notify(Notification::DragEnded { object }));
+        }
+    }
     commands.entity(listener.target()).remove::<Target>();
 }
 
@@ -118,4 +141,27 @@ pub fn drag_system(
         let orthogonal_vector = (drag_com_offset) - (drag_com_offset).project_onto(drag_impulse);
         target_force.torque_impulse = orthogonal_vector.cross(drag_impulse);
     }
-}
\ No newline at end of file
+}
+/// Shows a grab cursor over draggable entities and a grabbing cursor while one is pressed or
+/// dragged. The icon is only set when this changes, so that other systems can set it otherwise.
+pub fn drag_cursor_system(
+    draggables: Query<&PickingInteraction, With<Draggable>>,
+    targets: Query<(), With<Target>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut icon: Local<CursorIcon>,
+) {
+    let pressed = !targets.is_empty() ||
+        draggables.iter().any(|interaction| *interaction == PickingInteraction::Pressed);
+    let hovered = draggables.iter().any(|interaction| *interaction == PickingInteraction::Hovered);
+    let next_icon = match (pressed, hovered) {
+        (true, _) => CursorIcon::Grabbing,
+        (false, true) => CursorIcon::Grab,
+        (false, false) => CursorIcon::Default,
+    };
+    if next_icon != *icon {
+        *icon = next_icon;
+        if let Ok(mut window) = windows.get_single_mut() {
+            window.cursor.icon = next_icon;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{query::With, system::{Local, Query, Res}};
+use bevy_input::touch::Touches;
+use bevy_math::Vec2;
+
+use bevy_worker::drag::Target;
+
+use crate::camera::PanOrbitCamera;
+
+/// Recognizes touch gestures and accumulates them into the [`PanOrbitCamera`]s. Moving one finger
+/// orbits the camera (unless that finger is dragging an object), moving two fingers pans the
+/// camera, and pinching two fingers zooms the camera.
+pub fn accumulate_touch_events_system(
+    touches: Res<Touches>,
+    drag_targets: Query<(), With<Target>>,
+    mut last_positions: Local<HashMap<u64, Vec2>>,
+    mut query: Query<&mut PanOrbitCamera>,
+) {
+    /* track the positions ourselves since `Touch::previous_position` only reflects the last
+       event and several move events can arrive between two updates */
+    let positions = touches.iter()
+        .map(|touch| (touch.id(), touch.position()))
+        .collect::<HashMap<_, _>>();
+    let last_positions = std::mem::replace(&mut *last_positions, positions.clone());
+
+    let mut pan = Vec2::ZERO;
+    let mut rotation_move = Vec2::ZERO;
+    let mut scroll = 0.0;
+    /* the one finger gesture plays the same role as the orbit button */
+    let orbit_button_changed = (last_positions.len() == 1) != (positions.len() == 1);
+
+    /* only interpret the movement if the same fingers were down during the last update */
+    let moves = positions.iter()
+        .filter_map(|(id, position)| last_positions.get(id).map(|last| (*last, *position)))
+        .collect::<Vec<_>>();
+    if moves.len() == positions.len() && moves.len() == last_positions.len() {
+        match moves[..] {
+            [(last, position)] if drag_targets.is_empty() => {
+                rotation_move += position - last;
+            }
+            [(last_a, position_a), (last_b, position_b)] => {
+                pan += (position_a + position_b - last_a - last_b) * 0.5;
+                scroll += position_a.distance(position_b) - last_a.distance(last_b);
+            }
+            _ => {}
+        }
+    }
+
+    for mut camera in query.iter_mut() {
+        camera.orbit_button_changed |= orbit_button_changed;
+        camera.pan += 2.0 * pan;
+        camera.rotation_move += 2.0 * rotation_move;
+        camera.scroll += 0.1 * scroll;
+    }
+}
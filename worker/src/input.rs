@@ -0,0 +1,152 @@
+use bevy_ecs::{system::Resource, world::World};
+use bevy_input::{keyboard::KeyboardInput, mouse::{MouseButton, MouseButtonInput, MouseMotion, MouseScrollUnit, MouseWheel}, touch::{ForceTouch, TouchInput, TouchPhase}, ButtonState};
+use bevy_math::Vec2;
+use bevy_window::{CursorEntered, CursorGrabMode, CursorLeft, CursorMoved, Window, WindowResized, WindowScaleFactorChanged};
+use shared::BevyError;
+use thread_safe::ThreadSafe;
+
+use crate::{keyboard, offscreen};
+
+/// The buffer that was shared using [`shared::Bevy::attach_input_buffer`]
+#[derive(Resource)]
+pub(crate) struct InputBuffer(pub ThreadSafe<shared::InputBuffer>);
+
+/// Processes the events that the main thread wrote into the input buffer since the last update.
+/// Runs before the input systems so that the events are seen by the current update. It is also
+/// called before processing events that were sent over RPC, since the main thread falls back to
+/// RPC when the buffer is full (e.g., while the update loop is paused) and these events must not
+/// overtake the events that are still in the buffer.
+pub(crate) fn drain_input_buffer_system(world: &mut World) {
+    let Some(input_buffer) = world.get_resource::<InputBuffer>() else {
+        return;
+    };
+    let input_buffer = input_buffer.0.get_ref();
+    let events: Vec<_> = std::iter::from_fn(|| input_buffer.pop()).collect();
+    for (window, event) in events {
+        /* events for windows that were detached in the meantime are dropped */
+        if let Err(error) = process_event(world, window, event) {
+            tracing::warn!("could not process buffered input event: {}", error);
+        }
+    }
+}
+
+/// Converts an event from the main thread into the corresponding Bevy event for the given window
+pub(crate) fn process_event(world: &mut World, window: shared::WindowId, event: shared::Event) -> Result<(), BevyError> {
+    /* get the targeted window */
+    let window_id = world.resource::<offscreen::Windows>()
+        .get(window)
+        .ok_or(match window {
+            shared::WindowId::PRIMARY => BevyError::NoPrimaryWindow,
+            window => BevyError::WindowNotFound(window),
+        })?;
+    let mut window = world.get_mut::<Window>(window_id)
+        .ok_or(BevyError::WindowNotFound(window))?;
+    /* convert and send event */
+    match event {
+        shared::Event::Resize { width, height, physical_size } => {
+            match physical_size {
+                Some((physical_width, physical_height)) =>
+                    window.resolution.set_physical_resolution(physical_width, physical_height),
+                None => window.resolution.set(width as f32, height as f32),
+            }
+            let (width, height) = (window.width(), window.height());
+            world.send_event(WindowResized {
+                window: window_id,
+                width,
+                height,
+            });
+        },
+        shared::Event::ScaleFactorChanged { scale_factor } => {
+            if !(scale_factor.is_finite() && scale_factor > 0.0) {
+                return Err(BevyError::InvalidEvent(format!("invalid scale factor {}", scale_factor)));
+            }
+            /* the logical size is retained, so the offscreen canvas is resized to the new physical size */
+            window.resolution.set_scale_factor_override(Some(scale_factor as f32));
+            world.send_event(WindowScaleFactorChanged {
+                window: window_id,
+                scale_factor,
+            });
+        },
+        shared::Event::CursorEntered => {
+            world.send_event(CursorEntered { window: window_id });
+        }
+        shared::Event::CursorLeft => {
+            world.send_event(CursorLeft { window: window_id });
+        },
+        shared::Event::CursorMoved { delta, position: (x, y) } => {
+            world.send_event(CursorMoved {
+                window: window_id,
+                position: Vec2::new(x as f32, y as f32),
+                delta: delta.map(|(x, y)| Vec2::new(x as f32, y as f32))
+            });
+        }
+        shared::Event::MouseMotion { delta: (delta_x, delta_y) } => {
+            world.send_event(MouseMotion {
+                delta: Vec2::new(delta_x as f32, delta_y as f32)
+            });
+        },
+        shared::Event::PointerLockChanged { locked } => {
+            window.cursor.grab_mode = match locked {
+                true => CursorGrabMode::Locked,
+                false => CursorGrabMode::None,
+            };
+        },
+        shared::Event::MouseWheel { delta: (delta_x, delta_y), unit } => {
+            let unit = match unit {
+                0 => MouseScrollUnit::Pixel,
+                1 => MouseScrollUnit::Line,
+                unit => return Err(BevyError::InvalidEvent(format!("invalid scroll unit {}", unit)))
+            };
+            world.send_event(MouseWheel {
+                window: window_id,
+                unit,
+                x: delta_x as f32,
+                y: delta_y as f32,
+            });
+        }
+        shared::Event::MouseButton { pressed, button } => {
+            world.send_event(MouseButtonInput {
+                state: match pressed {
+                    true => ButtonState::Pressed,
+                    false => ButtonState::Released,
+                },
+                button: match button {
+                    0 => MouseButton::Left,
+                    1 => MouseButton::Middle,
+                    2 => MouseButton::Right,
+                    3 => MouseButton::Back,
+                    4 => MouseButton::Forward,
+                    other => MouseButton::Other(other as u16)
+                },
+                window: window_id,
+            });
+        }
+        shared::Event::KeyboardInput { pressed, code, key, repeat: _, modifiers } => {
+            keyboard::release_stale_modifiers(world, window_id, &modifiers);
+            world.send_event(KeyboardInput {
+                key_code: keyboard::key_code(&code),
+                logical_key: keyboard::logical_key(&key),
+                state: match pressed {
+                    true => ButtonState::Pressed,
+                    false => ButtonState::Released,
+                },
+                window: window_id,
+            });
+        }
+        shared::Event::Touch { phase, id, position: (x, y), force } => {
+            world.send_event(TouchInput {
+                phase: match phase {
+                    shared::TouchPhase::Started => TouchPhase::Started,
+                    shared::TouchPhase::Moved => TouchPhase::Moved,
+                    shared::TouchPhase::Ended => TouchPhase::Ended,
+                    shared::TouchPhase::Canceled => TouchPhase::Canceled,
+                },
+                position: Vec2::new(x as f32, y as f32),
+                window: window_id,
+                force: force.map(ForceTouch::Normalized),
+                id: id as u64,
+            });
+        }
+    }
+    Ok(())
+}
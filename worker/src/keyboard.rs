@@ -0,0 +1,60 @@
+use bevy_ecs::{entity::Entity, world::World};
+use bevy_input::{keyboard::{Key, KeyCode, KeyboardInput, NativeKey, NativeKeyCode}, ButtonInput, ButtonState};
+use bevy_reflect::{DynamicEnum, FromReflect};
+
+/// Converts a `KeyboardEvent.code` value into a [`KeyCode`]. Apart from the meta keys, the
+/// variant names of [`KeyCode`] match the values used by the browser.
+pub fn key_code(code: &str) -> KeyCode {
+    let variant = match code {
+        "MetaLeft" | "OSLeft" => "SuperLeft",
+        "MetaRight" | "OSRight" => "SuperRight",
+        other => other,
+    };
+    KeyCode::from_reflect(&DynamicEnum::new(variant, ()))
+        .unwrap_or(KeyCode::Unidentified(NativeKeyCode::Unidentified))
+}
+
+/// Converts a `KeyboardEvent.key` value into a [`Key`]. Named keys match the variant names of
+/// [`Key`], anything else is a character produced by the current keyboard layout.
+pub fn logical_key(key: &str) -> Key {
+    match key {
+        " " => Key::Space,
+        "Meta" => Key::Super,
+        "Dead" => Key::Dead(None),
+        "Unidentified" => Key::Unidentified(NativeKey::Unidentified),
+        other => Key::from_reflect(&DynamicEnum::new(other, ()))
+            .unwrap_or_else(|| match other.chars().count() {
+                1 => Key::Character(other.into()),
+                _ => Key::Unidentified(NativeKey::Web(other.into())),
+            })
+    }
+}
+
+/// Releases modifier keys that Bevy still considers pressed, but that the browser reports as
+/// released. This happens when a key is released while the canvas does not have focus.
+pub fn release_stale_modifiers(
+    world: &mut World,
+    window: Entity,
+    modifiers: &shared::Modifiers,
+) {
+    let stale = [
+        (modifiers.shift, [KeyCode::ShiftLeft, KeyCode::ShiftRight], Key::Shift),
+        (modifiers.control, [KeyCode::ControlLeft, KeyCode::ControlRight], Key::Control),
+        (modifiers.alt, [KeyCode::AltLeft, KeyCode::AltRight], Key::Alt),
+        (modifiers.meta, [KeyCode::SuperLeft, KeyCode::SuperRight], Key::Super),
+    ]
+        .into_iter()
+        .filter(|(held, ..)| !held)
+        .flat_map(|(_, key_codes, logical_key)| key_codes
+            .map(|key_code| (key_code, logical_key.clone())))
+        .filter(|(key_code, _)| world.resource::<ButtonInput<KeyCode>>().pressed(*key_code))
+        .collect::<Vec<_>>();
+    for (key_code, logical_key) in stale {
+        world.send_event(KeyboardInput {
+            key_code,
+            logical_key,
+            state: ButtonState::Released,
+            window,
+        });
+    }
+}
@@ -0,0 +1,383 @@
+use std::{cell::RefCell, pin::pin, rc::Rc, time::Duration};
+use bevy_app::{App, Last, PluginsState, PreUpdate, Startup, Update};
+use bevy_core::FrameCount;
+use bevy_ecs::{schedule::IntoSystemConfigs, system::RunSystemOnce};
+use bevy_input::InputSystem;
+use bevy_mod_picking::DefaultPickingPlugins;
+use bevy_rapier3d::plugin::{NoUserData, RapierConfiguration, RapierPhysicsPlugin};
+use bevy_render::renderer::RenderAdapterInfo;
+use bevy_window::WindowCreated;
+use futures::{future, lock::Mutex, FutureExt};
+use lifecycle::Lifecycle;
+use shared::BevyError;
+use thread_safe::ThreadSafe;
+use wasm_bindgen::prelude::*;
+
+pub mod world;
+pub mod command;
+pub mod drag;
+mod cursor;
+mod offscreen;
+mod input;
+mod keyboard;
+pub mod notify;
+mod lifecycle;
+mod pacing;
+mod physics;
+mod panic;
+mod logging;
+mod diagnostics;
+
+/// Configures the app once the canvas has been transferred to the worker
+type Configure = Box<dyn Fn(&mut App)>;
+
+/// Runs a Bevy app inside of a web worker that is controlled over RPC from the main thread. The
+/// worker adds the plugins for rendering onto the offscreen canvas, physics, picking, and
+/// diagnostics when it is initialised, the app-specific systems (e.g., for setting up the world
+/// in the [`world::Setup`] schedule) are added by the closures passed to [`WorkerApp::configure`].
+#[derive(Default)]
+pub struct WorkerApp {
+    configure: Vec<Configure>,
+}
+
+impl WorkerApp {
+    pub fn new() -> WorkerApp {
+        WorkerApp::default()
+    }
+
+    /// Adds a closure that configures the app during initialisation, after the plugins of the
+    /// worker have been added
+    pub fn configure(mut self, configure: impl Fn(&mut App) + 'static) -> WorkerApp {
+        self.configure.push(Box::new(configure));
+        self
+    }
+
+    /// Starts serving requests from the main thread
+    pub fn run(self) {
+        /* configure panic hook for debugging and for reporting crashes to the main thread */
+        panic::set_hook();
+        /* configure logging to the console and to the main thread */
+        logging::init();
+        /* start Bevy */
+        /* the runner must be `Send`, however, it is only ever called on this thread */
+        let configure = ThreadSafe::new(self.configure);
+        App::new()
+            .set_runner(|app| wasm_bindgen_futures::spawn_local(async move {
+                let scope = js_sys::global().dyn_into::<web_sys::DedicatedWorkerGlobalScope>().unwrap();
+                let interface = web_rpc::Interface::new(scope).await;
+                let app = Rc::new(Mutex::new(app));
+                /* create the RPC interface, the client is used to call the service on the main thread */
+                let (main_client, server) = web_rpc::Builder::new(interface)
+                    .with_service::<shared::BevyService<_>>(BevyServerImpl {
+                        app: app.clone(),
+                        lifecycle: Rc::new(RefCell::new(Lifecycle::Uninitialised)),
+                        configure: configure.into_inner(),
+                    })
+                    .with_client::<shared::MainClient>()
+                    .build();
+                notify::set_main_client(main_client.clone());
+                app.lock().await
+                    .add_plugins(notify::NotifyPlugin::new(main_client));
+                server.await
+            }))
+            .run();
+    }
+}
+
+/// How long to wait for the plugins (e.g., the renderer) to become ready during initialisation
+const PLUGIN_INIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct BevyServerImpl {
+    app: Rc<Mutex<App>>,
+    lifecycle: Rc<RefCell<Lifecycle>>,
+    configure: Vec<Configure>,
+}
+
+/// Returns an error if the rendering backend that the worker was built with is not supported
+#[cfg(not(feature = "webgpu"))]
+fn backend_available() -> Result<(), BevyError> {
+    /* probe for WebGL2 on a separate canvas so that the renderer can create its own context */
+    let webgl2_available = web_sys::OffscreenCanvas::new(1, 1)
+        .and_then(|probe| probe.get_context("webgl2"))
+        .is_ok_and(|context| context.is_some());
+    match webgl2_available {
+        true => Ok(()),
+        false => Err(BevyError::WebGl2Unavailable),
+    }
+}
+
+/// Returns an error if the rendering backend that the worker was built with is not supported
+#[cfg(feature = "webgpu")]
+fn backend_available() -> Result<(), BevyError> {
+    /* the adapter is requested by the renderer, so only check that the API is exposed */
+    let webgpu_available = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("navigator"))
+        .and_then(|navigator| js_sys::Reflect::has(&navigator, &JsValue::from_str("gpu")))
+        .unwrap_or(false);
+    match webgpu_available {
+        true => Ok(()),
+        false => Err(BevyError::WebGpuUnavailable),
+    }
+}
+
+impl BevyServerImpl {
+    /// Adds the plugins and the systems of the worker to the app, followed by those added by the
+    /// closures passed to [`WorkerApp::configure`], and waits until the plugins are ready
+    async fn build(
+        &self,
+        app: &mut App,
+        canvas: web_sys::OffscreenCanvas,
+        arena: shared::ArenaDesc,
+    ) -> Result<(), BevyError> {
+        app
+            /* configure simulator */
+            .add_plugins(bevy_core::TaskPoolPlugin::default())
+            .add_plugins(bevy_core::TypeRegistrationPlugin)
+            .add_plugins(bevy_core::FrameCountPlugin)
+            .add_plugins(bevy_time::TimePlugin)
+            .add_plugins(bevy_transform::TransformPlugin)
+            .add_plugins(bevy_hierarchy::HierarchyPlugin)
+            .add_plugins(bevy_diagnostic::DiagnosticsPlugin)
+            .add_plugins(bevy_input::InputPlugin)
+            .add_plugins(offscreen::OffscreenPlugin::new(canvas))
+            .add_plugins(bevy_asset::AssetPlugin::default())
+            .add_plugins(bevy_scene::ScenePlugin)
+            .add_plugins(bevy_render::RenderPlugin::default())
+            .add_plugins(bevy_render::texture::ImagePlugin::default())
+            .add_plugins(bevy_core_pipeline::CorePipelinePlugin)
+            .add_plugins(bevy_pbr::PbrPlugin::default())
+            .add_plugins(bevy_gltf::GltfPlugin::default())
+            .add_plugins(bevy_gizmos::GizmoPlugin)
+            /* simulation configuration */
+            .insert_resource(RapierConfiguration {
+                query_pipeline_active: true,
+                ..Default::default()
+            })
+            .insert_resource(physics::Physics(shared::PhysicsConfig::default()))
+            .add_systems(PreUpdate, physics::configure_physics_system)
+            .add_systems(PreUpdate, input::drain_input_buffer_system.before(InputSystem))
+            .add_plugins(DefaultPickingPlugins)
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .add_plugins(diagnostics::DiagnosticsStreamPlugin)
+            //.add_plugins(RapierDebugRenderPlugin::default())
+            .insert_resource(world::Arena(arena))
+            .init_resource::<world::Objects>()
+            .init_schedule(world::Setup)
+            .add_systems(Startup, world::run_setup)
+            // custom systems for dragging entities
+            .add_systems(Update, drag::drag_system)
+            .add_systems(Update, drag::drag_cursor_system)
+            .add_systems(Last, cursor::forward_cursor_system);
+        for configure in &self.configure {
+            configure(app);
+        }
+        /* wait until initialisation is complete before releasing the app lock */
+        let plugins_ready = async {
+            while app.plugins_state() != PluginsState::Ready {
+                gloo_timers::future::sleep(Duration::default()).await;
+            }
+        };
+        let timeout = gloo_timers::future::sleep(PLUGIN_INIT_TIMEOUT);
+        if let future::Either::Right(_) = future::select(pin!(plugins_ready), timeout).await {
+            return Err(BevyError::PluginInit(format!("plugins were not ready after {:?}", PLUGIN_INIT_TIMEOUT)));
+        }
+        app.finish();
+        app.cleanup();
+        Ok(())
+    }
+}
+
+impl shared::Bevy for BevyServerImpl {
+    async fn init(
+        &self,
+        canvas: web_sys::OffscreenCanvas,
+        arena: shared::ArenaDesc,
+    ) -> Result<(), BevyError> {
+        let mut app_locked = self.app.lock().await;
+        match *self.lifecycle.borrow() {
+            Lifecycle::Uninitialised => {},
+            Lifecycle::Failed => return Err(BevyError::InitFailed),
+            _ => return Err(BevyError::AlreadyInitialised),
+        }
+        backend_available()?;
+        world::validate(&arena)?;
+        /* plugins can only be added once, so a failure from here on can not be recovered from */
+        *self.lifecycle.borrow_mut() = Lifecycle::Failed;
+        self.build(&mut app_locked, canvas, arena).await?;
+        *self.lifecycle.borrow_mut() = Lifecycle::Stopped;
+
+        Ok(())
+    }
+
+    async fn attach_canvas(&self, canvas: web_sys::OffscreenCanvas) -> Result<shared::WindowId, BevyError> {
+        let world = &mut self.app.lock().await.world;
+        self.lifecycle.borrow().ensure_initialised()?;
+        /* WebGL2 presents every surface using the context of the first canvas */
+        let backend = world.resource::<RenderAdapterInfo>().backend.to_str();
+        if backend == "gl" {
+            return Err(BevyError::MultipleCanvasesUnsupported(backend.to_owned()));
+        }
+        /* the app is responsible for spawning a camera that renders onto the window */
+        let (window_id, window) = offscreen::spawn_window(world, canvas);
+        world.send_event(WindowCreated { window });
+        Ok(window_id)
+    }
+
+    async fn detach_canvas(&self, window: shared::WindowId) -> Result<(), BevyError> {
+        let world = &mut self.app.lock().await.world;
+        self.lifecycle.borrow().ensure_initialised()?;
+        offscreen::despawn_window(world, window)
+    }
+    
+    async fn start(&self, pacing: shared::Pacing) -> Result<(), BevyError> {
+        let mut lifecycle = self.lifecycle.borrow_mut();
+        match *lifecycle {
+            Lifecycle::Uninitialised | Lifecycle::Failed => return Err(BevyError::NotInitialised),
+            Lifecycle::Running(_) | Lifecycle::Paused(_) => return Err(BevyError::AlreadyRunning),
+            Lifecycle::Stopped => {}
+        }
+        let pacing = match pacing {
+            shared::Pacing::AnimationFrame if !pacing::AnimationFrameStream::is_supported() => {
+                tracing::warn!("animation frames are not available in this worker, updating every {:?} instead",
+                    pacing::FALLBACK_INTERVAL);
+                shared::Pacing::Interval(pacing::FALLBACK_INTERVAL)
+            },
+            pacing => pacing,
+        };
+        let (update_loop, update_loop_handle) = future::abortable(lifecycle::update_loop(
+            self.app.clone(),
+            self.lifecycle.clone(),
+            pacing
+        ));
+        wasm_bindgen_futures::spawn_local(update_loop.map(drop));
+        *lifecycle = Lifecycle::Running(update_loop_handle);
+        Ok(())
+    }
+
+    async fn pause(&self) -> Result<(), BevyError> {
+        self.lifecycle.borrow_mut().pause()
+    }
+
+    async fn resume(&self) -> Result<(), BevyError> {
+        self.lifecycle.borrow_mut().resume()
+    }
+
+    async fn stop(&self) -> Result<u32, BevyError> {
+        self.lifecycle.borrow_mut().stop()?;
+        Ok(self.app.lock().await.world.resource::<FrameCount>().0)
+    }
+
+    async fn step(&self, frames: u32) -> Result<u32, BevyError> {
+        match *self.lifecycle.borrow() {
+            Lifecycle::Uninitialised | Lifecycle::Failed => return Err(BevyError::NotInitialised),
+            Lifecycle::Paused(_) => {}
+            _ => return Err(BevyError::NotPaused),
+        }
+        let mut app_locked = self.app.lock().await;
+        input::drain_input_buffer_system(&mut app_locked.world);
+        app_locked.world.insert_resource(physics::Stepping);
+        for _ in 0..frames {
+            app_locked.update();
+        }
+        app_locked.world.remove_resource::<physics::Stepping>();
+        Ok(app_locked.world.resource::<FrameCount>().0)
+    }
+
+    async fn set_physics(&self, config: shared::PhysicsConfig) -> Result<(), BevyError> {
+        self.lifecycle.borrow().ensure_initialised()?;
+        physics::validate(&config)?;
+        self.app.lock().await.world.resource_mut::<physics::Physics>().0 = config;
+        Ok(())
+    }
+
+    async fn physics(&self) -> Result<shared::PhysicsConfig, BevyError> {
+        self.lifecycle.borrow().ensure_initialised()?;
+        Ok(self.app.lock().await.world.resource::<physics::Physics>().0)
+    }
+
+    async fn reset(&self) -> Result<(), BevyError> {
+        let mut app_locked = self.app.lock().await;
+        self.lifecycle.borrow().ensure_initialised()?;
+        /* only the canvases and the state of the connection to the main thread are carried over */
+        let world = &mut app_locked.world;
+        let canvases = offscreen::canvases(world)?;
+        let arena = world.remove_resource::<world::Arena>().unwrap().0;
+        let objects = world.resource::<world::Objects>().continued();
+        let main_client = world.resource::<notify::MainClient>().get().clone();
+        let input_buffer = world.remove_resource::<input::InputBuffer>();
+        let subscription = world.remove_resource::<diagnostics::Subscription>().unwrap();
+        /* drop the previous app (and its renderer) before rebuilding it on a fresh world */
+        *app_locked = App::new();
+        app_locked.add_plugins(notify::NotifyPlugin::new(main_client));
+        if let Err(error) = self.build(&mut app_locked, canvases.primary.clone(), arena).await {
+            /* the previous app is gone, so the worker must be restarted */
+            let mut lifecycle = self.lifecycle.borrow_mut();
+            if let Lifecycle::Running(update_loop) | Lifecycle::Paused(update_loop) = &*lifecycle {
+                update_loop.abort();
+            }
+            *lifecycle = Lifecycle::Failed;
+            return Err(error);
+        }
+        let world = &mut app_locked.world;
+        offscreen::restore_windows(world, canvases);
+        world.insert_resource(objects);
+        world.insert_resource(subscription);
+        if let Some(input_buffer) = input_buffer {
+            world.insert_resource(input_buffer);
+        }
+        Ok(())
+    }
+
+    async fn subscribe_diagnostics(&self, interval: Option<Duration>) -> Result<(), BevyError> {
+        self.lifecycle.borrow().ensure_initialised()?;
+        self.app.lock().await.world.insert_resource(diagnostics::Subscription::new(interval));
+        Ok(())
+    }
+
+    async fn set_log_filter(&self, filter: String) -> Result<(), BevyError> {
+        logging::set_filter(&filter)
+    }
+
+    async fn process_event(&self, window: shared::WindowId, event: shared::Event) -> Result<(), BevyError> {
+        /* wait for the world to be ready */
+        let world = &mut self.app.lock().await.world;
+        self.lifecycle.borrow().ensure_initialised()?;
+        input::drain_input_buffer_system(world);
+        input::process_event(world, window, event)
+    }
+
+    async fn process_events(&self, window: shared::WindowId, events: Vec<shared::Event>) -> Result<(), BevyError> {
+        let world = &mut self.app.lock().await.world;
+        self.lifecycle.borrow().ensure_initialised()?;
+        input::drain_input_buffer_system(world);
+        /* the remaining events are processed even if an event is invalid, the first error is returned */
+        let mut result = Ok(());
+        for event in events {
+            let processed = input::process_event(world, window, event);
+            result = result.and(processed);
+        }
+        result
+    }
+
+    async fn attach_input_buffer(&self, buffer: js_sys::SharedArrayBuffer) -> Result<(), BevyError> {
+        let world = &mut self.app.lock().await.world;
+        self.lifecycle.borrow().ensure_initialised()?;
+        let input_buffer = shared::InputBuffer::from_buffer(buffer)?;
+        world.insert_resource(input::InputBuffer(ThreadSafe::new(input_buffer)));
+        Ok(())
+    }
+
+    async fn spawn_object(&self, object: shared::ObjectDesc) -> Result<shared::ObjectId, BevyError> {
+        self.lifecycle.borrow().ensure_initialised()?;
+        self.app.lock().await.world.run_system_once_with(object, world::spawn_object)
+    }
+
+    async fn despawn(&self, object: shared::ObjectId) -> Result<(), BevyError> {
+        self.lifecycle.borrow().ensure_initialised()?;
+        self.app.lock().await.world.run_system_once_with(object, world::despawn_object)
+    }
+
+    async fn send_command(&self, tag: String, payload: String) -> Result<(), BevyError> {
+        self.lifecycle.borrow().ensure_initialised()?;
+        command::send_command(&mut self.app.lock().await.world, &tag, &payload)
+    }
+}
@@ -0,0 +1,99 @@
+use std::{cell::RefCell, rc::Rc};
+use bevy_app::App;
+use futures::{future::AbortHandle, lock::Mutex, StreamExt};
+use gloo_timers::future::IntervalStream;
+use shared::{BevyError, Pacing};
+
+use crate::pacing::AnimationFrameStream;
+
+/// The lifecycle of the app and its update loop
+pub enum Lifecycle {
+    /// the app has not been initialised
+    Uninitialised,
+    /// initialisation failed after the plugins had been added, so it can not be retried
+    Failed,
+    /// the app has been initialised, but the update loop is not running
+    Stopped,
+    /// the update loop is running and updating the app
+    Running(AbortHandle),
+    /// the update loop is running, but the app is not being updated
+    Paused(AbortHandle),
+}
+
+impl Lifecycle {
+    pub fn is_initialised(&self) -> bool {
+        !matches!(self, Lifecycle::Uninitialised | Lifecycle::Failed)
+    }
+
+    /// Returns an error if the app has not been initialised
+    pub fn ensure_initialised(&self) -> Result<(), BevyError> {
+        match self.is_initialised() {
+            true => Ok(()),
+            false => Err(BevyError::NotInitialised),
+        }
+    }
+
+    pub fn pause(&mut self) -> Result<(), BevyError> {
+        match self {
+            Lifecycle::Running(update_loop) => {
+                let update_loop = update_loop.clone();
+                *self = Lifecycle::Paused(update_loop);
+                Ok(())
+            }
+            Lifecycle::Uninitialised | Lifecycle::Failed => Err(BevyError::NotInitialised),
+            _ => Err(BevyError::NotRunning)
+        }
+    }
+
+    pub fn resume(&mut self) -> Result<(), BevyError> {
+        match self {
+            Lifecycle::Paused(update_loop) => {
+                let update_loop = update_loop.clone();
+                *self = Lifecycle::Running(update_loop);
+                Ok(())
+            }
+            Lifecycle::Uninitialised | Lifecycle::Failed => Err(BevyError::NotInitialised),
+            _ => Err(BevyError::NotPaused)
+        }
+    }
+
+    /// Aborts the update loop if it is running (or paused)
+    pub fn stop(&mut self) -> Result<(), BevyError> {
+        match self {
+            Lifecycle::Running(update_loop) | Lifecycle::Paused(update_loop) => {
+                update_loop.abort();
+                *self = Lifecycle::Stopped;
+                Ok(())
+            }
+            Lifecycle::Uninitialised | Lifecycle::Failed => Err(BevyError::NotInitialised),
+            Lifecycle::Stopped => Err(BevyError::NotRunning)
+        }
+    }
+}
+
+/// Updates the app at the rate given by `pacing` until the loop is aborted. Updates are skipped
+/// while the lifecycle is paused.
+pub async fn update_loop(
+    app: Rc<Mutex<App>>,
+    lifecycle: Rc<RefCell<Lifecycle>>,
+    pacing: Pacing,
+) {
+    let mut update = match pacing {
+        Pacing::Interval(update_interval) =>
+            IntervalStream::new(update_interval.as_millis() as u32).boxed_local(),
+        Pacing::AnimationFrame =>
+            AnimationFrameStream::new().map(drop).boxed_local(),
+    };
+    loop {
+        /* suspend until the next update is due */
+        update.next().await;
+        if matches!(*lifecycle.borrow(), Lifecycle::Paused(_)) {
+            continue;
+        }
+        /* lock the app and update bevy's world */
+        let mut app_locked = app.lock().await;
+        app_locked.update();
+        /* at this point the lock is dropped so that events can be sent to
+           the bevy world while we wait to do the next update */
+    }
+}
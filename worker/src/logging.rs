@@ -0,0 +1,85 @@
+use std::fmt;
+
+use once_cell::sync::OnceCell;
+use shared::{BevyError, LogLevel, LogRecord};
+use tracing::{field::{Field, Visit}, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, prelude::*, reload, EnvFilter, Layer, Registry};
+
+use crate::notify;
+
+/// Handle for replacing the filter of the subscriber at runtime
+static FILTER: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+/// Configures logging to the console and to the main thread
+pub fn init() {
+    let (filter, handle) = reload::Layer::new(EnvFilter::from(shared::DEFAULT_LOG_FILTER));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer()
+            .with_ansi(true)
+            .without_time()
+            .with_writer(tracing_web::MakeConsoleWriter))
+        .with(ForwardLayer)
+        .init();
+    let _ = FILTER.set(handle);
+}
+
+/// Replaces the filter that was installed by [`init`]
+pub fn set_filter(filter: &str) -> Result<(), BevyError> {
+    let filter = EnvFilter::try_new(filter)
+        .map_err(|error| BevyError::InvalidLogFilter(error.to_string()))?;
+    FILTER.get()
+        .ok_or_else(|| BevyError::InvalidLogFilter(String::from("logging has not been initialised")))?
+        .reload(filter)
+        .map_err(|error| BevyError::InvalidLogFilter(error.to_string()))
+}
+
+/// Forwards log records to the main thread. Records that are emitted before the RPC interface has
+/// been built are only written to the console.
+struct ForwardLayer;
+
+impl<S: Subscriber> Layer<S> for ForwardLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let record = LogRecord {
+            level: match *metadata.level() {
+                Level::ERROR => LogLevel::Error,
+                Level::WARN => LogLevel::Warn,
+                Level::INFO => LogLevel::Info,
+                Level::DEBUG => LogLevel::Debug,
+                Level::TRACE => LogLevel::Trace,
+            },
+            target: metadata.target().to_owned(),
+            message: fields.into_message(),
+        };
+        notify::with_main_client(|client| client.log(record));
+    }
+}
+
+/// Collects the message and the other fields of an event
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Fields {
+    fn into_message(self) -> String {
+        std::iter::once(self.message)
+            .chain(self.fields)
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => self.fields.push(format!("{}={:?}", name, value)),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use bevy_core_pipeline::core_3d::Camera3dBundle;
+use bevy_ecs::{component::Component, entity::Entity, event::EventReader, query::With, system::{Commands, Query, Res}};
+use bevy_hierarchy::DespawnRecursiveExt;
+use bevy_math::{UVec2, Vec3};
+use bevy_render::camera::{Camera, ClearColorConfig, OrthographicProjection, Projection, RenderTarget, ScalingMode, Viewport};
+use bevy_transform::components::Transform;
+use bevy_window::{PrimaryWindow, Window, WindowCreated, WindowRef};
+
+use bevy_worker::{command::RpcCommandEvent, world::{self, Arena}};
+use shared::{ArenaDesc, Minimap};
+
+/// The share of the width of the primary window that is covered by the minimap
+const MINIMAP_SIZE: f32 = 0.25;
+/// The distance between the minimap and the top right corner of the primary window in logical pixels
+const MINIMAP_MARGIN: f32 = 10.0;
+
+/// Marks the camera that renders the minimap into a corner of the primary window
+#[derive(Component)]
+pub struct MinimapCamera;
+
+/// Spawns a camera that shows the whole arena from above on each window that is attached after
+/// the primary window
+pub fn spawn_top_down_camera_system(
+    mut created: EventReader<WindowCreated>,
+    primary_window: Query<(), With<PrimaryWindow>>,
+    mut commands: Commands,
+    arena: Res<Arena>,
+) {
+    for WindowCreated { window } in created.read() {
+        if primary_window.contains(*window) {
+            continue;
+        }
+        commands.spawn(top_down_camera(&arena.0, Camera {
+            target: RenderTarget::Window(WindowRef::Entity(*window)),
+            ..Default::default()
+        }));
+    }
+}
+
+/// Shows or hides the minimap, which is rendered by a second camera onto the primary window so
+/// that it also works with the WebGL2 backend, which can not render onto more than one canvas
+pub fn minimap_system(
+    mut minimaps: EventReader<RpcCommandEvent<Minimap>>,
+    cameras: Query<Entity, With<MinimapCamera>>,
+    mut commands: Commands,
+    arena: Res<Arena>,
+) {
+    let Some(RpcCommandEvent(Minimap { visible })) = minimaps.read().last() else {
+        return;
+    };
+    match (*visible, cameras.get_single()) {
+        (true, Err(_)) => {
+            commands
+                .spawn(top_down_camera(&arena.0, Camera {
+                    /* render after the main camera without clearing its image */
+                    order: 1,
+                    clear_color: ClearColorConfig::None,
+                    ..Default::default()
+                }))
+                .insert(MinimapCamera);
+        },
+        (false, Ok(camera)) => commands.entity(camera).despawn_recursive(),
+        _ => {},
+    }
+}
+
+/// Places the minimap in the top right corner of the primary window
+pub fn update_minimap_viewport_system(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<&mut Camera, With<MinimapCamera>>,
+) {
+    let (Ok(window), Ok(mut camera)) = (windows.get_single(), cameras.get_single_mut()) else {
+        return;
+    };
+    let scale_factor = window.scale_factor();
+    let width = window.physical_width();
+    let margin = (MINIMAP_MARGIN * scale_factor) as u32;
+    let size = ((width as f32 * MINIMAP_SIZE) as u32)
+        .min(window.physical_height().saturating_sub(2 * margin))
+        .max(1);
+    let viewport = Viewport {
+        physical_position: UVec2::new(width.saturating_sub(size + margin), margin),
+        physical_size: UVec2::splat(size),
+        ..Default::default()
+    };
+    /* only assign the viewport when it changes to avoid triggering change detection */
+    if camera.viewport.as_ref().map(|current| (current.physical_position, current.physical_size)) !=
+        Some((viewport.physical_position, viewport.physical_size)) {
+        camera.viewport = Some(viewport);
+    }
+}
+
+/// An orthographic camera that shows the whole arena from above
+fn top_down_camera(arena: &ArenaDesc, camera: Camera) -> Camera3dBundle {
+    let (center, size) = world::bounds(arena);
+    let center = Vec3::new(center.x, 0.0, center.y);
+    Camera3dBundle {
+        camera,
+        projection: Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin { min_width: size.x, min_height: size.y },
+            ..Default::default()
+        }),
+        /* negative z is up so that the view matches the initial view of the main camera */
+        transform: Transform::from_translation(center + 10.0 * Vec3::Y)
+            .looking_at(center, Vec3::NEG_Z),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+
+use bevy_app::{App, Last, Plugin};
+use bevy_ecs::{event::{Event, EventReader}, system::{Res, Resource}};
+use thread_safe::ThreadSafe;
+
+/// Sending this event from a system forwards the notification to the main thread at the end of
+/// the current update
+#[derive(Event, Debug, Clone)]
+pub struct Notify(pub shared::Notification);
+
+/// Client for the service that is implemented on the main thread
+#[derive(Resource)]
+pub struct MainClient(ThreadSafe<shared::MainClient>);
+
+impl MainClient {
+    pub fn get(&self) -> &shared::MainClient {
+        self.0.get_ref()
+    }
+}
+
+thread_local! {
+    /// The client used to report to the main thread from outside of the app, e.g., from the panic
+    /// hook or from the tracing layer. It is set once the RPC interface has been built.
+    static MAIN_CLIENT: RefCell<Option<shared::MainClient>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_main_client(main_client: shared::MainClient) {
+    MAIN_CLIENT.with(|client| *client.borrow_mut() = Some(main_client));
+}
+
+/// Calls `f` with the client if it has been set. The call is skipped if the client is being set
+/// further up the stack, e.g., when a panic occurs while the client is being replaced.
+pub(crate) fn with_main_client(f: impl FnOnce(&shared::MainClient)) {
+    let _ = MAIN_CLIENT.try_with(|client| {
+        if let Ok(client) = client.try_borrow() {
+            if let Some(client) = client.as_ref() {
+                f(client);
+            }
+        }
+    });
+}
+
+pub struct NotifyPlugin {
+    client: ThreadSafe<shared::MainClient>,
+}
+
+impl NotifyPlugin {
+    pub fn new(client: shared::MainClient) -> NotifyPlugin {
+        NotifyPlugin {
+            client: ThreadSafe::new(client)
+        }
+    }
+}
+
+impl Plugin for NotifyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(MainClient(ThreadSafe::new(self.client.get_ref().clone())))
+            .add_event::<Notify>()
+            .add_systems(Last, forward_notifications_system);
+    }
+}
+
+fn forward_notifications_system(
+    mut notifications: EventReader<Notify>,
+    main_client: Res<MainClient>,
+) {
+    for Notify(notification) in notifications.read() {
+        main_client.get().notify(notification.clone());
+    }
+}
@@ -1,12 +1,41 @@
-use bevy_app::{App, Plugin};
+use std::collections::HashMap;
+
+use bevy_app::{App, Last, Plugin};
+use bevy_ecs::{component::Component, entity::Entity, query::Changed, system::{Query, Resource}, world::World};
+use bevy_hierarchy::DespawnRecursiveExt;
+use bevy_render::camera::{Camera, RenderTarget};
 use bevy_window::{   
     CursorEntered, CursorLeft, CursorMoved, 
-    PrimaryWindow, RawHandleWrapper, Window, WindowResolution,
+    PrimaryWindow, RawHandleWrapper, Window, WindowRef, WindowResolution,
     WindowClosed, WindowScaleFactorChanged, WindowCreated, WindowResized,
 };
 use thread_safe::ThreadSafe;
 use web_sys::OffscreenCanvas;
 use raw_window_handle::{RawDisplayHandle, RawWindowHandle, WebDisplayHandle, WebOffscreenCanvasWindowHandle};
+use shared::{BevyError, WindowId};
+
+/// The offscreen canvas that a window renders onto
+#[derive(Component)]
+pub struct Canvas(ThreadSafe<OffscreenCanvas>);
+
+impl Canvas {
+    pub fn get(&self) -> &OffscreenCanvas {
+        self.0.get_ref()
+    }
+}
+
+/// Maps the ids of the windows that were created for the offscreen canvases to their entities
+#[derive(Resource, Default)]
+pub struct Windows {
+    next_id: u64,
+    entities: HashMap<WindowId, Entity>,
+}
+
+impl Windows {
+    pub fn get(&self, window: WindowId) -> Option<Entity> {
+        self.entities.get(&window).copied()
+    }
+}
 
 pub struct OffscreenPlugin {
     canvas: ThreadSafe<OffscreenCanvas>,
@@ -22,26 +51,127 @@ impl OffscreenPlugin {
 
 impl Plugin for OffscreenPlugin {
     fn build(&self, simulator: &mut App) {
-        let window_handle =
-            WebOffscreenCanvasWindowHandle::from_wasm_bindgen_0_2(self.canvas.get_ref());
         simulator
+            .init_resource::<Windows>()
             .add_event::<WindowResized>()
             .add_event::<WindowCreated>()
             .add_event::<WindowClosed>()
             .add_event::<WindowScaleFactorChanged>()
             .add_event::<CursorEntered>()
             .add_event::<CursorLeft>()
-            .add_event::<CursorMoved>();
+            .add_event::<CursorMoved>()
+            .add_systems(Last, resize_canvas_system);
+        /* the first window that is spawned is assigned WindowId::PRIMARY */
+        let (_, window) = spawn_window(&mut simulator.world, self.canvas.get_ref().clone());
         simulator.world
-            .spawn(Window {
-                resolution: WindowResolution::new(500.0, 500.0),
-                ..Default::default()
-            })
-            .insert(PrimaryWindow)
-            .insert(RawHandleWrapper {
-                window_handle: RawWindowHandle::WebOffscreenCanvas(window_handle),
-                display_handle: RawDisplayHandle::Web(WebDisplayHandle::new()),
-            });
+            .entity_mut(window)
+            .insert(PrimaryWindow);
+    }
+}
+
+/// Spawns a window that renders onto the given canvas and returns its id and entity
+pub fn spawn_window(world: &mut World, canvas: OffscreenCanvas) -> (WindowId, Entity) {
+    let mut windows = world.resource_mut::<Windows>();
+    let id = WindowId(windows.next_id);
+    windows.next_id += 1;
+    let window = Window {
+        resolution: WindowResolution::new(500.0, 500.0),
+        ..Default::default()
+    };
+    (id, insert_window(world, id, window, canvas))
+}
+
+fn insert_window(world: &mut World, id: WindowId, window: Window, canvas: OffscreenCanvas) -> Entity {
+    let window_handle = WebOffscreenCanvasWindowHandle::from_wasm_bindgen_0_2(&canvas);
+    let window = world
+        .spawn(window)
+        .insert(Canvas(ThreadSafe::new(canvas)))
+        .insert(RawHandleWrapper {
+            window_handle: RawWindowHandle::WebOffscreenCanvas(window_handle),
+            display_handle: RawDisplayHandle::Web(WebDisplayHandle::new()),
+        })
+        .id();
+    world.resource_mut::<Windows>().entities.insert(id, window);
+    window
+}
+
+/// The windows and the canvases of an app that is being rebuilt
+pub struct Canvases {
+    next_id: u64,
+    pub primary: OffscreenCanvas,
+    /// the windows (including the primary window) in the order in which they were created
+    windows: Vec<(WindowId, Window, OffscreenCanvas)>,
+}
+
+/// Returns the windows and their canvases so that they can be restored in a new app, the main
+/// thread does not resend the size of a canvas unless it changes. Returns an error if the canvas
+/// of the primary window has been detached.
+pub fn canvases(world: &World) -> Result<Canvases, BevyError> {
+    let windows = world.resource::<Windows>();
+    let primary = windows.get(WindowId::PRIMARY).ok_or(BevyError::NoPrimaryWindow)?;
+    let mut restored = windows.entities.iter()
+        .map(|(id, entity)| {
+            let window = world.get::<Window>(*entity).unwrap().clone();
+            (*id, window, world.get::<Canvas>(*entity).unwrap().get().clone())
+        })
+        .collect::<Vec<_>>();
+    restored.sort_by_key(|(WindowId(id), _, _)| *id);
+    Ok(Canvases {
+        next_id: windows.next_id,
+        primary: world.get::<Canvas>(primary).unwrap().get().clone(),
+        windows: restored,
+    })
+}
+
+/// Restores the windows of a previous app, keeping their ids. The primary window has already been
+/// spawned by the [`OffscreenPlugin`], the app is notified by a [`WindowCreated`] event for each of
+/// the other windows.
+pub fn restore_windows(world: &mut World, Canvases { next_id, windows, .. }: Canvases) {
+    for (id, window, canvas) in windows {
+        match world.resource::<Windows>().get(id) {
+            Some(entity) => {
+                world.entity_mut(entity).insert(window);
+            },
+            None => {
+                let window = insert_window(world, id, window, canvas);
+                world.send_event(WindowCreated { window });
+            },
+        }
+    }
+    world.resource_mut::<Windows>().next_id = next_id;
+}
+
+/// Despawns a window and the cameras that render onto it. The renderer releases the surface of the
+/// window when it receives the [`WindowClosed`] event. Cameras that target the primary window
+/// indirectly (i.e., using [`WindowRef::Primary`]) are part of the scene and are retained.
+pub fn despawn_window(world: &mut World, window: WindowId) -> Result<(), BevyError> {
+    let entity = world.resource_mut::<Windows>()
+        .entities
+        .remove(&window)
+        .ok_or(BevyError::WindowNotFound(window))?;
+    let cameras = world.query::<(Entity, &Camera)>()
+        .iter(world)
+        .filter(|(_, camera)| matches!(camera.target, RenderTarget::Window(WindowRef::Entity(target)) if target == entity))
+        .map(|(camera, _)| camera)
+        .collect::<Vec<_>>();
+    for camera in cameras {
+        world.entity_mut(camera).despawn_recursive();
     }
+    world.despawn(entity);
+    world.send_event(WindowClosed { window: entity });
+    Ok(())
 }
 
+/// Resizes the backing store of the offscreen canvas to the physical size of its window
+fn resize_canvas_system(
+    windows: Query<(&Window, &Canvas), Changed<Window>>,
+) {
+    for (window, canvas) in windows.iter() {
+        let canvas = canvas.get();
+        /* resizing the canvas clears it, so only resize it if the size has changed */
+        if canvas.width() != window.physical_width() || canvas.height() != window.physical_height() {
+            canvas.set_width(window.physical_width());
+            canvas.set_height(window.physical_height());
+        }
+    }
+}
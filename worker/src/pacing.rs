@@ -0,0 +1,66 @@
+use std::{pin::Pin, task::{Context, Poll}, time::Duration};
+use futures::{channel::mpsc, Stream, StreamExt};
+use wasm_bindgen::prelude::*;
+use web_sys::DedicatedWorkerGlobalScope;
+
+/// The interval at which the app is updated if animation frames are unavailable (about 60 Hz)
+pub const FALLBACK_INTERVAL: Duration = Duration::from_millis(16);
+
+/// A stream that yields the timestamp of each animation frame of the worker. Animation frames are
+/// synchronized with the refresh rate of the display and are not delivered while the page is
+/// hidden.
+pub struct AnimationFrameStream {
+    scope: DedicatedWorkerGlobalScope,
+    callback: Closure<dyn FnMut(f64)>,
+    frames: mpsc::UnboundedReceiver<f64>,
+    /// the id of the pending request (if any) so that it can be cancelled on drop
+    request_id: Option<i32>,
+}
+
+impl AnimationFrameStream {
+    pub fn new() -> AnimationFrameStream {
+        let (frames_tx, frames) = mpsc::unbounded();
+        AnimationFrameStream {
+            scope: js_sys::global().unchecked_into(),
+            callback: Closure::new(move |timestamp: f64| {
+                let _ = frames_tx.unbounded_send(timestamp);
+            }),
+            frames,
+            request_id: None,
+        }
+    }
+
+    /// Returns whether the worker provides `requestAnimationFrame`, which is not the case in every
+    /// browser. Without it, the stream would never yield a frame.
+    pub fn is_supported() -> bool {
+        js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("requestAnimationFrame"))
+            .unwrap_or(false)
+    }
+}
+
+impl Stream for AnimationFrameStream {
+    type Item = f64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.request_id.is_none() {
+            this.request_id = this.scope
+                .request_animation_frame(this.callback.as_ref().unchecked_ref())
+                .ok();
+        }
+        let frame = this.frames.poll_next_unpin(cx);
+        if frame.is_ready() {
+            this.request_id = None;
+        }
+        frame
+    }
+}
+
+impl Drop for AnimationFrameStream {
+    fn drop(&mut self) {
+        /* the callback is about to be dropped, so it must not be called by the browser */
+        if let Some(request_id) = self.request_id {
+            let _ = self.scope.cancel_animation_frame(request_id);
+        }
+    }
+}
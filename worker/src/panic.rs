@@ -0,0 +1,21 @@
+use std::panic::PanicHookInfo;
+
+use crate::notify;
+
+/// Installs a panic hook that logs panics to the console and forwards them to the main thread
+pub fn set_hook() {
+    std::panic::set_hook(Box::new(hook));
+}
+
+fn hook(info: &PanicHookInfo) {
+    console_error_panic_hook::hook(info);
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => String::from("Box<dyn Any>"),
+        },
+    };
+    let location = info.location().map(ToString::to_string);
+    notify::with_main_client(|client| client.panic(message, location));
+}
@@ -0,0 +1,62 @@
+use bevy_ecs::{change_detection::DetectChanges, system::{Local, Res, ResMut, Resource}};
+use bevy_rapier3d::plugin::{RapierConfiguration, TimestepMode};
+use shared::{BevyError, PhysicsConfig, Timestep};
+
+/// The physics configuration that is applied to Rapier before each update
+#[derive(Resource)]
+pub struct Physics(pub PhysicsConfig);
+
+/// Inserted while the app is being stepped manually so that each update advances the simulation
+/// by exactly one step
+#[derive(Resource)]
+pub struct Stepping;
+
+/// Checks that a configuration can be applied to Rapier, an infinite timestep or time scale would
+/// turn the state of the simulation into NaN
+pub fn validate(config: &PhysicsConfig) -> Result<(), BevyError> {
+    let dt = match config.timestep {
+        Timestep::Fixed { dt } => dt,
+        Timestep::Variable { max_dt } => max_dt,
+        Timestep::Interpolated { dt } => dt,
+    };
+    /* fixed timesteps are scaled by the time scale, so their product must be finite too */
+    let finite = dt.is_finite() && config.time_scale.is_finite() && (dt * config.time_scale).is_finite();
+    match finite && dt > 0.0 && config.substeps > 0 && config.time_scale >= 0.0 {
+        true => Ok(()),
+        false => Err(BevyError::InvalidPhysicsConfig),
+    }
+}
+
+pub fn configure_physics_system(
+    physics: Res<Physics>,
+    stepping: Option<Res<Stepping>>,
+    mut stepped: Local<bool>,
+    mut rapier_configuration: ResMut<RapierConfiguration>,
+) {
+    let stepping = stepping.is_some();
+    if !physics.is_changed() && stepping == *stepped {
+        return;
+    }
+    *stepped = stepping;
+    let PhysicsConfig { timestep, substeps, time_scale, active } = physics.0;
+    rapier_configuration.timestep_mode = match (timestep, stepping) {
+        /* Rapier does not scale fixed timesteps, so scale the timestep instead */
+        (Timestep::Fixed { dt }, _) |
+        (Timestep::Variable { max_dt: dt }, true) |
+        (Timestep::Interpolated { dt }, true) => TimestepMode::Fixed {
+            dt: dt * time_scale,
+            substeps
+        },
+        (Timestep::Variable { max_dt }, false) => TimestepMode::Variable {
+            max_dt,
+            time_scale,
+            substeps
+        },
+        (Timestep::Interpolated { dt }, false) => TimestepMode::Interpolated {
+            dt,
+            time_scale,
+            substeps
+        },
+    };
+    rapier_configuration.physics_pipeline_active = active;
+}
@@ -0,0 +1,87 @@
+use bevy_asset::Assets;
+use bevy_core_pipeline::core_3d::Camera3dBundle;
+use bevy_ecs::{event::EventWriter, system::{Commands, Res, ResMut}};
+use bevy_hierarchy::BuildChildren;
+use bevy_math::{Quat, Vec3};
+use bevy_pbr::{DirectionalLight, DirectionalLightBundle, PointLightBundle, StandardMaterial};
+use bevy_render::{mesh::Mesh, view::VisibilityBundle};
+use bevy_transform::components::Transform;
+
+use bevy_worker::{notify::Notify, world::{self, Arena, Objects}};
+use shared::{Notification, ObjectDesc, Shape};
+
+use crate::camera::PanOrbitCamera;
+
+pub fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut notifications: EventWriter<Notify>,
+    mut objects: ResMut<Objects>,
+    arena: Res<Arena>,
+) {
+    let translation = Vec3::new(1.5, 2.0, 2.5);
+    let focus = Vec3::ZERO;
+    let transform = Transform::from_translation(translation)
+        .looking_at(focus, Vec3::Y);
+
+    commands
+        .spawn(Camera3dBundle {
+            transform,
+            ..Default::default()
+        })
+        .insert(PanOrbitCamera {
+            focus,
+            radius: translation.length(),
+            ..Default::default()
+        })
+        .insert(VisibilityBundle::default())
+        .with_children(|commands| {
+            commands.spawn(DirectionalLightBundle {
+                directional_light: DirectionalLight {
+                    shadows_enabled: false,
+                    illuminance: 1000.0,
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(-2.5, 2.5, 2.5)
+                    .looking_at(Vec3::ZERO, Vec3::Y),
+                    ..Default::default()
+            });
+        });
+
+    //lights (note ambient light use in the app resources)
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(5.0, 5.0, 0.0),
+        ..Default::default()
+    });
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(-5.0, 5.0, 0.0),
+        ..Default::default()
+    });
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(0.0, 5.0, 5.0),
+        ..Default::default()
+    });
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(0.0, 5.0, -5.0),
+        ..Default::default()
+    });
+    
+    world::spawn_arena(&mut commands, &mut meshes, &mut materials, &arena.0);
+
+    // Add balls
+    const BALL_RADIUS: f32 = 0.075;
+    const BALL_MASS: f32 = 0.1;
+
+    world::spawn(&mut commands, &mut meshes, &mut materials, &mut objects, ObjectDesc {
+        shape: Shape::Ball { radius: BALL_RADIUS },
+        mass: BALL_MASS,
+        restitution: 1.0,
+        color: [0.7, 0.0, 0.0, 1.0],
+        translation: Vec3::new(0.0, BALL_RADIUS, 0.0),
+        rotation: Quat::IDENTITY,
+        draggable: true,
+    });
+
+    notifications.send(Notify(Notification::Loaded));
+}
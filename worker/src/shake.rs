@@ -0,0 +1,26 @@
+use bevy_ecs::{entity::Entity, event::EventReader, system::{Commands, Query}};
+use bevy_math::Vec3;
+use bevy_rapier3d::dynamics::{ExternalImpulse, RigidBody};
+use bevy_worker::command::RpcCommandEvent;
+use shared::Shake;
+
+/// Pushes each of the dynamic bodies in a random horizontal direction
+pub fn shake_system(
+    mut shakes: EventReader<RpcCommandEvent<Shake>>,
+    bodies: Query<(Entity, &RigidBody)>,
+    mut commands: Commands,
+) {
+    for RpcCommandEvent(Shake { impulse }) in shakes.read() {
+        for (entity, body) in bodies.iter() {
+            if *body != RigidBody::Dynamic {
+                continue;
+            }
+            let angle = js_sys::Math::random() as f32 * std::f32::consts::TAU;
+            /* the impulse is reset by the physics backend once it has been applied */
+            commands.entity(entity).insert(ExternalImpulse {
+                impulse: Vec3::new(angle.cos(), 0.0, angle.sin()) * *impulse,
+                ..Default::default()
+            });
+        }
+    }
+}
@@ -1,151 +1,299 @@
+use std::collections::HashMap;
+
 use bevy_asset::Assets;
-use bevy_core_pipeline::core_3d::Camera3dBundle;
-use bevy_ecs::system::{Commands, ResMut};
-use bevy_hierarchy::BuildChildren;
-use bevy_math::{primitives::{Cuboid, Sphere, Plane3d}, Vec2, Vec3};
-use bevy_pbr::{DirectionalLight, DirectionalLightBundle, PbrBundle, PointLightBundle, StandardMaterial};
-use bevy_rapier3d::{geometry::{Collider, CollisionGroups, Group}, prelude::{ColliderMassProperties, Restitution, RigidBody}};
-use bevy_render::{color::Color, mesh::{Mesh, Meshable}, prelude::SpatialBundle, view::VisibilityBundle};
+use bevy_ecs::{entity::Entity, event::{EventReader, EventWriter}, schedule::ScheduleLabel, system::{Commands, In, Res, ResMut, Resource}, world::World};
+use bevy_hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy_math::{primitives::{Cuboid, Cylinder, Sphere, Plane3d}, Quat, Vec2};
+use bevy_pbr::{PbrBundle, StandardMaterial};
+use bevy_rapier3d::{geometry::{ActiveEvents, Collider, CollisionGroups, Group}, pipeline::CollisionEvent, prelude::{ColliderMassProperties, Restitution, RigidBody}};
+use bevy_render::{color::Color, mesh::{Mesh, Meshable}, prelude::SpatialBundle};
 use bevy_transform::components::Transform;
 
-use crate::{camera::PanOrbitCamera, drag::DraggableBundle};
+use shared::{ArenaDesc, BevyError, FloorDesc, Notification, ObjectDesc, ObjectId, Shape, WallDesc};
+
+use crate::{drag::DraggableBundle, notify::Notify};
 
-const WORLD_SIZE: Vec2 = Vec2::new(2.5, 2.5);
 const STATIC_GROUP: Group = Group::GROUP_1;
 const OBJECT_GROUP: Group = Group::GROUP_2;
-const WALL_HEIGHT: f32 = 0.075;
-const WALL_WIDTH: f32 = 0.075;
-const WALL_COLOR: Color = Color::rgb(0.7, 0.7, 0.7);
 
-pub fn setup(
+/// The description of the arena that is generated during setup
+#[derive(Resource)]
+pub struct Arena(pub ArenaDesc);
+
+/// The schedule that sets up the world, it runs on startup. When the world is reset, the app is
+/// rebuilt and the schedule runs again on a fresh world.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Setup;
+
+/// Runs the [`Setup`] schedule
+pub(crate) fn run_setup(world: &mut World) {
+    world.run_schedule(Setup);
+}
+
+/// Maps the ids of the objects in the world to their entities and back
+#[derive(Resource, Default)]
+pub struct Objects {
+    next_id: u64,
+    entities: HashMap<ObjectId, Entity>,
+    ids: HashMap<Entity, ObjectId>,
+}
+
+impl Objects {
+    /// Returns the id of the object that is represented by the given entity
+    pub fn id(&self, entity: Entity) -> Option<ObjectId> {
+        self.ids.get(&entity).copied()
+    }
+
+    /// Returns an empty map that continues to assign ids after those of this map, so that the ids
+    /// of the objects in a previous world are not reused
+    pub(crate) fn continued(&self) -> Objects {
+        Objects { next_id: self.next_id, ..Default::default() }
+    }
+
+    fn remove(&mut self, object: ObjectId) -> Option<Entity> {
+        let entity = self.entities.remove(&object)?;
+        self.ids.remove(&entity);
+        Some(entity)
+    }
+}
+
+pub(crate) fn spawn_object(
+    In(object): In<ObjectDesc>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let translation = Vec3::new(1.5, 2.0, 2.5);
-    let focus = Vec3::ZERO;
-    let transform = Transform::from_translation(translation)
-        .looking_at(focus, Vec3::Y);
+    mut objects: ResMut<Objects>,
+) -> Result<ObjectId, BevyError> {
+    validate_object(&object)?;
+    Ok(spawn(&mut commands, &mut meshes, &mut materials, &mut objects, object))
+}
 
-    commands
-        .spawn(Camera3dBundle {
-            transform,
-            ..Default::default()
-        })
-        .insert(PanOrbitCamera {
-            focus,
-            radius: translation.length(),
-            ..Default::default()
-        })
-        .insert(VisibilityBundle::default())
-        .with_children(|commands| {
-            commands.spawn(DirectionalLightBundle {
-                directional_light: DirectionalLight {
-                    shadows_enabled: false,
-                    illuminance: 1000.0,
-                    ..Default::default()
-                },
-                transform: Transform::from_xyz(-2.5, 2.5, 2.5)
-                    .looking_at(Vec3::ZERO, Vec3::Y),
-                    ..Default::default()
-            });
-        });
+pub(crate) fn despawn_object(
+    In(object): In<ObjectId>,
+    mut commands: Commands,
+    mut objects: ResMut<Objects>,
+) -> Result<(), BevyError> {
+    /* the entity may have been despawned by another system in the meantime */
+    let entity = objects.remove(object)
+        .and_then(|entity| commands.get_entity(entity))
+        .ok_or(BevyError::ObjectNotFound(object))?;
+    entity.despawn_recursive();
+    Ok(())
+}
 
-    //lights (note ambient light use in the app resources)
-    commands.spawn(PointLightBundle {
-        transform: Transform::from_xyz(5.0, 5.0, 0.0),
-        ..Default::default()
-    });
-    commands.spawn(PointLightBundle {
-        transform: Transform::from_xyz(-5.0, 5.0, 0.0),
-        ..Default::default()
-    });
-    commands.spawn(PointLightBundle {
-        transform: Transform::from_xyz(0.0, 5.0, 5.0),
-        ..Default::default()
-    });
-    commands.spawn(PointLightBundle {
-        transform: Transform::from_xyz(0.0, 5.0, -5.0),
-        ..Default::default()
-    });
-    
-    // north wall
-    commands
-        .spawn(Collider::cuboid((WORLD_SIZE.x - WALL_WIDTH) * 0.5, WALL_HEIGHT * 0.5, WALL_WIDTH * 0.5))
-        .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
-        .insert(PbrBundle {
-            mesh: meshes.add(Mesh::from(Cuboid::new(WORLD_SIZE.x - WALL_WIDTH, WALL_HEIGHT, WALL_WIDTH))),
-            material: materials.add(WALL_COLOR),
-            transform: Transform::from_xyz(-WALL_WIDTH * 0.5, WALL_HEIGHT * 0.5, (-WORLD_SIZE.y + WALL_WIDTH) * 0.5),
-            ..Default::default()
-        });
+/// Checks that an arena can be generated, i.e., that the bounds of the arena are finite and that
+/// the walls and the shapes of the obstacles have a positive size
+pub(crate) fn validate(arena: &ArenaDesc) -> Result<(), BevyError> {
+    let invalid = |reason: &str| Err(BevyError::InvalidArena(reason.to_owned()));
+    let WallDesc { height, thickness, .. } = arena.wall;
+    if !(height.is_finite() && height > 0.0) {
+        return invalid("the wall height must be positive");
+    }
+    if !(thickness.is_finite() && thickness > 0.0) {
+        return invalid("the wall thickness must be positive");
+    }
+    match &arena.boundary {
+        Some(boundary) if boundary.len() < 3 =>
+            return invalid("the boundary must have at least three corners"),
+        Some(boundary) if !boundary.iter().all(|corner| corner.is_finite()) =>
+            return invalid("the corners of the boundary must be finite"),
+        Some(_) => {},
+        None if !(arena.size.is_finite() && arena.size.cmpgt(Vec2::splat(thickness)).all()) =>
+            return invalid("the size must be larger than the wall thickness"),
+        None => {},
+    }
+    for obstacle in &arena.obstacles {
+        if let Err(dimension) = validate_shape(obstacle.shape) {
+            return invalid(&format!("the {dimension} of the obstacles must be positive"));
+        }
+        if !(obstacle.translation.is_finite() && obstacle.rotation.is_finite()) {
+            return invalid("the transforms of the obstacles must be finite");
+        }
+    }
+    Ok(())
+}
 
-    // east wall
-    commands
-        .spawn(Collider::cuboid(WALL_WIDTH * 0.5, WALL_HEIGHT * 0.5, (WORLD_SIZE.y - WALL_WIDTH) * 0.5))
-        .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
-        .insert(PbrBundle {
-            mesh: meshes.add(Mesh::from(Cuboid::new(WALL_WIDTH, WALL_HEIGHT, WORLD_SIZE.y - WALL_WIDTH))),
-            material: materials.add(WALL_COLOR),
-            transform: Transform::from_xyz((WORLD_SIZE.x - WALL_WIDTH) * 0.5, WALL_HEIGHT * 0.5, -WALL_WIDTH * 0.5),
-            ..Default::default()
-        });
+/// Checks that an object can be spawned, i.e., that its shape has a positive size and that its
+/// mass, restitution, and transform are finite
+fn validate_object(object: &ObjectDesc) -> Result<(), BevyError> {
+    let invalid = |reason: &str| Err(BevyError::InvalidObject(reason.to_owned()));
+    if let Err(dimension) = validate_shape(object.shape) {
+        return invalid(&format!("the {dimension} must be positive"));
+    }
+    if !(object.mass.is_finite() && object.mass > 0.0) {
+        return invalid("the mass must be positive");
+    }
+    if !object.restitution.is_finite() {
+        return invalid("the restitution must be finite");
+    }
+    if !(object.translation.is_finite() && object.rotation.is_finite()) {
+        return invalid("the transform must be finite");
+    }
+    Ok(())
+}
 
-    // south wall
-    commands
-        .spawn(Collider::cuboid((WORLD_SIZE.x - WALL_WIDTH) * 0.5, WALL_HEIGHT * 0.5, WALL_WIDTH * 0.5))
-        .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
-        .insert(PbrBundle {
-            mesh: meshes.add(Mesh::from(Cuboid::new(WORLD_SIZE.x - WALL_WIDTH, WALL_HEIGHT, WALL_WIDTH))),
-            material: materials.add(WALL_COLOR),
-            transform: Transform::from_xyz(WALL_WIDTH * 0.5, WALL_HEIGHT * 0.5, (WORLD_SIZE.y - WALL_WIDTH) * 0.5),
-            ..Default::default()
-        });
+/// Checks that the collider and the mesh of a shape can be created, Rapier panics if the size of
+/// a shape is negative. Returns the name of the dimension that is not finite and positive.
+pub(crate) fn validate_shape(shape: Shape) -> Result<(), &'static str> {
+    let positive = |value: f32| value.is_finite() && value > 0.0;
+    match shape {
+        Shape::Ball { radius } | Shape::Cylinder { radius, .. } if !positive(radius) => Err("radius"),
+        Shape::Cylinder { height, .. } if !positive(height) => Err("height"),
+        Shape::Cuboid { size } if !(positive(size.x) && positive(size.y) && positive(size.z)) => Err("size"),
+        _ => Ok(()),
+    }
+}
 
-    // west wall
-    commands
-        .spawn(Collider::cuboid(WALL_WIDTH * 0.5, WALL_HEIGHT * 0.5, (WORLD_SIZE.y - WALL_WIDTH) * 0.5))
-        .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
-        .insert(PbrBundle {
-            mesh: meshes.add(Mesh::from(Cuboid::new(WALL_WIDTH, WALL_HEIGHT, WORLD_SIZE.y - WALL_WIDTH))),
-            material: materials.add(WALL_COLOR),
-            transform: Transform::from_xyz((-WORLD_SIZE.x + WALL_WIDTH) * 0.5, WALL_HEIGHT * 0.5, WALL_WIDTH * 0.5),
-            ..Default::default()
-        });
+/// The corners of the centerline of the walls
+fn boundary(arena: &ArenaDesc) -> Vec<Vec2> {
+    /* by default, place the centerline of the walls inside the edges of the arena */
+    arena.boundary.clone().unwrap_or_else(|| {
+        let corner = (arena.size - arena.wall.thickness) * 0.5;
+        vec![
+            Vec2::new(-corner.x, -corner.y),
+            Vec2::new(corner.x, -corner.y),
+            Vec2::new(corner.x, corner.y),
+            Vec2::new(-corner.x, corner.y),
+        ]
+    })
+}
+
+/// The center and the size of the bounding box of the walls on the xz-plane
+pub fn bounds(arena: &ArenaDesc) -> (Vec2, Vec2) {
+    let (min, max) = boundary(arena).iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), corner| (min.min(*corner), max.max(*corner)));
+    ((min + max) * 0.5, max - min + arena.wall.thickness)
+}
+
+/// Spawns the walls, the floor, and the obstacles of an arena
+pub fn spawn_arena(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    arena: &ArenaDesc,
+) {
+    let WallDesc { height, thickness, color: [red, green, blue, alpha] } = arena.wall;
+    let wall_material = materials.add(Color::rgba(red, green, blue, alpha));
+    let boundary = boundary(arena);
+
+    // walls
+    for (start, end) in boundary.iter().zip(boundary.iter().cycle().skip(1)) {
+        let direction = *end - *start;
+        let center = (*start + *end) * 0.5;
+        /* extend each wall by its thickness so that the corners are closed */
+        let length = direction.length() + thickness;
+        commands
+            .spawn(Collider::cuboid(length * 0.5, height * 0.5, thickness * 0.5))
+            .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
+            .insert(PbrBundle {
+                mesh: meshes.add(Mesh::from(Cuboid::new(length, height, thickness))),
+                material: wall_material.clone(),
+                transform: Transform::from_xyz(center.x, height * 0.5, center.y)
+                    .with_rotation(Quat::from_rotation_y((-direction.y).atan2(direction.x))),
+                ..Default::default()
+            });
+    }
 
-    // floor
+    // floor (covers the bounding box of the walls)
+    let (floor_center, floor_size) = bounds(arena);
+    let FloorDesc { color: [red, green, blue, alpha], perceptual_roughness, metallic } = arena.floor;
     commands
-        .spawn(Collider::cuboid(0.5 * WORLD_SIZE.x, 0.1, 0.5 * WORLD_SIZE.y))
+        .spawn(Collider::cuboid(0.5 * floor_size.x, 0.1, 0.5 * floor_size.y))
         .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
-        .insert(SpatialBundle::from_transform(Transform::from_xyz(0.0, -0.1, 0.0)))
+        .insert(SpatialBundle::from_transform(Transform::from_xyz(floor_center.x, -0.1, floor_center.y)))
         .with_children(|commands| {
             commands.spawn(PbrBundle {
-                mesh: meshes.add(Plane3d::default().mesh().size(WORLD_SIZE.x, WORLD_SIZE.y)),
-                material: materials.add(Color::rgba(0.9, 0.9, 0.9, 1.0)),
+                mesh: meshes.add(Plane3d::default().mesh().size(floor_size.x, floor_size.y)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(red, green, blue, alpha),
+                    perceptual_roughness,
+                    metallic,
+                    ..Default::default()
+                }),
                 transform: Transform::from_xyz(0.0, 0.1, 0.0),
                 ..Default::default()
             });
         });
 
-    // Add balls
-    const BALL_RADIUS: f32 = 0.075;
-    const BALL_MASS: f32 = 0.1;
-    const BALL_COLOR: Color = Color::rgb(0.7, 0.0, 0.0);
+    // obstacles
+    for obstacle in &arena.obstacles {
+        let (collider, mesh) = shape(obstacle.shape);
+        let [red, green, blue, alpha] = obstacle.color;
+        commands
+            .spawn(collider)
+            .insert(CollisionGroups::new(STATIC_GROUP, OBJECT_GROUP))
+            .insert(PbrBundle {
+                mesh: meshes.add(mesh),
+                material: materials.add(Color::rgba(red, green, blue, alpha)),
+                transform: Transform::from_translation(obstacle.translation)
+                    .with_rotation(obstacle.rotation),
+                ..Default::default()
+            });
+    }
+}
+
+/// Creates the collider and the mesh for a shape
+fn shape(shape: Shape) -> (Collider, Mesh) {
+    match shape {
+        Shape::Ball { radius } =>
+            (Collider::ball(radius), Mesh::from(Sphere::new(radius))),
+        Shape::Cuboid { size } =>
+            (Collider::cuboid(size.x * 0.5, size.y * 0.5, size.z * 0.5), Mesh::from(Cuboid::from_size(size))),
+        Shape::Cylinder { radius, height } =>
+            (Collider::cylinder(height * 0.5, radius), Mesh::from(Cylinder::new(radius, height))),
+    }
+}
 
-    commands
-        .spawn(Collider::ball(BALL_RADIUS))
+/// Spawns an object and assigns it an id so that it can be despawned over RPC
+pub fn spawn(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    objects: &mut Objects,
+    object: ObjectDesc,
+) -> ObjectId {
+    let (collider, mesh) = shape(object.shape);
+    let [red, green, blue, alpha] = object.color;
+    let mut entity = commands.spawn(collider);
+    entity
         .insert(CollisionGroups::new(OBJECT_GROUP, OBJECT_GROUP | STATIC_GROUP))
-        .insert(Restitution::new(1.0))
+        .insert(Restitution::new(object.restitution))
+        .insert(ActiveEvents::COLLISION_EVENTS)
         .insert(RigidBody::Dynamic)
-        .insert(ColliderMassProperties::Mass(BALL_MASS))
-        .insert(DraggableBundle::default())
+        .insert(ColliderMassProperties::Mass(object.mass))
         .insert(PbrBundle {
-            mesh: meshes.add(Mesh::from(Sphere::new(BALL_RADIUS))),
-            material: materials.add(BALL_COLOR),
-            transform: Transform::from_xyz(0.0, BALL_RADIUS, 0.0),
+            mesh: meshes.add(mesh),
+            material: materials.add(Color::rgba(red, green, blue, alpha)),
+            transform: Transform::from_translation(object.translation)
+                .with_rotation(object.rotation),
             ..Default::default()
         });
+    if object.draggable {
+        entity.insert(DraggableBundle::default());
+    }
+    let id = ObjectId(objects.next_id);
+    objects.next_id += 1;
+    objects.entities.insert(id, entity.id());
+    objects.ids.insert(entity.id(), id);
+    id
+}
 
-
+/// Forwards the collisions between objects to the main thread as notifications
+pub fn notify_collisions_system(
+    mut collisions: EventReader<CollisionEvent>,
+    mut notifications: EventWriter<Notify>,
+    objects: Res<Objects>,
+) {
+    for collision in collisions.read() {
+        let (started, entity_a, entity_b) = match collision {
+            CollisionEvent::Started(entity_a, entity_b, _) => (true, entity_a, entity_b),
+            CollisionEvent::Stopped(entity_a, entity_b, _) => (false, entity_a, entity_b),
+        };
+        notifications.send(Notify(Notification::Collision {
+            started,
+            objects: (objects.id(*entity_a), objects.id(*entity_b)),
+        }));
+    }
 }
 